@@ -1,6 +1,6 @@
 use std::{process::Command, env, net::{TcpListener, SocketAddr, TcpStream}, io::{ErrorKind, Write, Read}, time::Duration, str::from_utf8};
 
-use crate::{init::Init, log::Log};
+use crate::{init::Init, log::Log, error::Error};
 
 pub struct Action {}
 
@@ -17,7 +17,7 @@ impl Action {
         let file = env::current_exe().unwrap();
         match Command::new(file).arg("go").current_dir(dir).spawn() {
             Ok(p) => println!("Сервер запущено. PID={}", p.id()),
-            Err(err) => log.exit(100, &err.to_string()),
+            Err(err) => log.exit(Error::ServerStart, &err.to_string()),
         };
     }
 
@@ -27,33 +27,68 @@ impl Action {
                 match tcp.write_all(b"stop") {
                     Ok(()) => {
                         if let Err(err) = tcp.set_read_timeout(Some(Duration::from_secs(30))) {
-                            log.exit(250, &err.to_string());
+                            log.exit(Error::IrcReadTimeout, &err.to_string());
                         }
                         let mut buffer: Vec<u8> = Vec::with_capacity(128);
                         match tcp.read_to_end(&mut buffer) {
                             Ok(size) => match size {
-                                0 => log.exit(206, ""),
+                                0 => log.exit(Error::IrcEmpty, ""),
                                 _ => {
                                     match from_utf8(&buffer[..size]) {
                                         Ok(s) => {
                                             match s.parse::<i32>() {
                                                 Ok(pid) => println!("Сервер зупинено. PID={}", pid),
-                                                Err(err) => log.exit(208, &err.to_string()),
+                                                Err(err) => log.exit(Error::IrcPid, &err.to_string()),
                                             };
                                         },
-                                        Err(err) => log.exit(207, &err.to_string()),
+                                        Err(err) => log.exit(Error::IrcData, &err.to_string()),
                                     };
                                 },
                             },
-                            Err(e) => log.exit(260, &e.to_string()),
+                            Err(e) => log.exit(Error::IrcRead, &e.to_string()),
                         };
                     },
-                    Err(err) => log.exit(203, &err.to_string()),
+                    Err(err) => log.exit(Error::IrcSend, &err.to_string()),
                 };
             },
             Err(err) => match err.kind() {
-                ErrorKind::TimedOut => log.exit(202, ""),
-                _ => log.exit(201, &err.to_string()),
+                ErrorKind::TimedOut => log.exit(Error::IrcTimeout, ""),
+                _ => log.exit(Error::IrcConnect, &err.to_string()),
+            },
+        };
+    }
+
+    pub fn limit(init: Init, log: Log) {
+        Action::report(init, log, b"limit");
+    }
+
+    pub fn cache(init: Init, log: Log) {
+        Action::report(init, log, b"cache");
+    }
+
+    // Відправити службову команду та вивести відповідь сервера
+    fn report(init: Init, log: Log, command: &[u8]) {
+        match TcpStream::connect_timeout(&SocketAddr::from(([127, 0, 0, 1], init.irc)), Duration::from_secs(2)) {
+            Ok(mut tcp) => {
+                if let Err(err) = tcp.write_all(command) {
+                    log.exit(Error::IrcSend, &err.to_string());
+                }
+                if let Err(err) = tcp.set_read_timeout(Some(Duration::from_secs(30))) {
+                    log.exit(Error::IrcReadTimeout, &err.to_string());
+                }
+                let mut buffer: Vec<u8> = Vec::with_capacity(4096);
+                match tcp.read_to_end(&mut buffer) {
+                    Ok(0) => log.exit(Error::IrcEmpty, ""),
+                    Ok(size) => match from_utf8(&buffer[..size]) {
+                        Ok(s) => println!("{}", s),
+                        Err(err) => log.exit(Error::IrcData, &err.to_string()),
+                    },
+                    Err(err) => log.exit(Error::IrcRead, &err.to_string()),
+                };
+            },
+            Err(err) => match err.kind() {
+                ErrorKind::TimedOut => log.exit(Error::IrcTimeout, ""),
+                _ => log.exit(Error::IrcConnect, &err.to_string()),
             },
         };
     }
@@ -0,0 +1,198 @@
+use std::{sync::{Arc, Mutex, RwLock, mpsc::{self, Receiver, Sender, RecvTimeoutError}}, thread::{self, JoinHandle}, time::{Duration, Instant}, fs::{File, OpenOptions}, io::Write};
+
+use mysql::Value;
+
+use crate::{init::Init, log::Log, db::DB, error::Error};
+
+pub const MS1000: std::time::Duration = Duration::from_millis(1000);
+pub const S30: std::time::Duration = Duration::from_secs(30);
+pub const AUDIT_BATCH: usize = 500;
+pub const AUDIT_MAX: usize = 100000;
+
+// Таблиця в базі db_log:
+//
+// CREATE TABLE price_audit (
+//     id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+//     dt DATETIME(3) NOT NULL,
+//     companyID INT UNSIGNED NOT NULL, userID INT UNSIGNED NOT NULL, targetID INT UNSIGNED NOT NULL,
+//     format VARCHAR(8) NOT NULL, volume VARCHAR(4) NOT NULL, lang VARCHAR(4) NOT NULL, flags VARCHAR(64) NOT NULL,
+//     cache TINYINT(1) NOT NULL, bytes BIGINT UNSIGNED NOT NULL, duration INT UNSIGNED NOT NULL,
+//     status SMALLINT UNSIGNED NOT NULL, code INT UNSIGNED NOT NULL, ip VARCHAR(45) NOT NULL,
+//     KEY company_dt (companyID, dt)
+// );
+const AUDIT_SQL: &str = "
+    INSERT INTO price_audit (dt, companyID, userID, targetID, format, volume, lang, flags, cache, bytes, duration, status, code, ip)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+";
+
+// Запис журналу завантажень
+#[derive(Debug, Default)]
+pub struct AuditRecord {
+    pub time: String,                   // Час запиту
+    pub company_id: u32,
+    pub user_id: u32,
+    pub target_id: u32,
+    pub format: String,
+    pub volume: String,
+    pub lang: String,
+    pub flags: String,                  // cur, nds, ean, api, pcvinga
+    pub hit: bool,                      // Прайс взято з кеша
+    pub bytes: usize,                   // Розмір відповіді
+    pub duration: u64,                  // Тривалість обробки (мс)
+    pub status: u16,                    // HTTP статус
+    pub code: u32,                      // Код помилки клієнта
+    pub ip: String,                     // REMOTE_ADDR
+}
+
+impl AuditRecord {
+    fn to_params(&self) -> Vec<Value> {
+        vec![
+            Value::from(&self.time), Value::from(self.company_id), Value::from(self.user_id), Value::from(self.target_id),
+            Value::from(&self.format), Value::from(&self.volume), Value::from(&self.lang), Value::from(&self.flags),
+            Value::from(self.hit), Value::from(self.bytes as u64), Value::from(self.duration), Value::from(self.status),
+            Value::from(self.code), Value::from(&self.ip),
+        ]
+    }
+
+    fn to_line(&self) -> String {
+        format!("{} {} company={} user={} target={} format={} full={} lang={} flags={} cache={} status={} code={} bytes={} ms={}\n",
+            self.time, if self.ip.len() > 0 { &self.ip } else { "-" }, self.company_id, self.user_id, self.target_id,
+            self.format, self.volume, self.lang, if self.flags.len() > 0 { &self.flags } else { "-" },
+            if self.hit { "HIT" } else { "MISS" }, self.status, self.code, self.bytes, self.duration)
+    }
+}
+
+// Асинхронний журнал завантажень
+#[derive(Debug)]
+pub struct Audit {
+    thread: Option<JoinHandle<()>>,
+    pub sender: Option<Sender<AuditRecord>>, // Канал для робочих потоків
+}
+
+impl Audit {
+    pub fn new(init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Arc<Mutex<Audit>> {
+        let (sender, receiver) = mpsc::channel();
+        let audit = Arc::new(Mutex::new(Audit {
+            thread: None,
+            sender: Some(sender),
+        }));
+        let thread = thread::spawn(move || {
+            Audit::run(receiver, init, log);
+        });
+        {
+            let mut a = Mutex::lock(&audit).unwrap();
+            a.thread.replace(thread);
+        }
+        audit
+    }
+
+    pub fn join(audit: Arc<Mutex<Audit>>) {
+        let thread;
+        {
+            let mut a = Mutex::lock(&audit).unwrap();
+            thread = a.thread.take();
+            a.sender = None;
+        }
+        if let Some(thread) = thread {
+            thread.join().unwrap();
+        }
+    }
+
+    // Потік працює, доки не закриються всі канали: Audit::join закриває власний, робочі потоки - свої після зупинки
+    fn run(receiver: Receiver<AuditRecord>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) {
+        let mut file;
+        {
+            let i = RwLock::read(&init).unwrap();
+            file = if i.audit_file { Some(AuditFile { name: format!("{}/access.log", i.dir), file: None }) } else { None };
+        }
+        let mut db: Option<DB> = None;
+        let mut batch: Vec<AuditRecord> = Vec::with_capacity(AUDIT_BATCH);
+        let mut last = Instant::now();
+        let mut wait = MS1000;
+        loop {
+            let mut stop = false;
+            match receiver.recv_timeout(MS1000) {
+                Ok(record) => {
+                    if let Some(file) = &mut file {
+                        file.write(&record, Arc::clone(&log));
+                    }
+                    batch.push(record);
+                },
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => stop = true,
+            }
+            if batch.len() > 0 && (stop || (batch.len() >= AUDIT_BATCH && wait == MS1000) || last.elapsed() >= wait) {
+                // Після невдалого запису база даних не турбується 30 секунд
+                wait = if Audit::flush(&mut db, &mut batch, Arc::clone(&init), Arc::clone(&log)) { MS1000 } else { S30 };
+                last = Instant::now();
+                // Файл перевідкривається раз на пакет, щоб після зовнішньої ротації писати в новий access.log
+                if let Some(file) = &mut file {
+                    file.file = None;
+                }
+            }
+            if stop {
+                if batch.len() > 0 {
+                    let l = RwLock::read(&log).unwrap();
+                    l.write(Error::AuditLost, &batch.len().to_string());
+                }
+                break;
+            }
+        }
+    }
+
+    // Записати пакет в базу даних, при невдачі пакет залишається для наступної спроби
+    fn flush(db: &mut Option<DB>, batch: &mut Vec<AuditRecord>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> bool {
+        if db.is_none() {
+            let i = RwLock::read(&init).unwrap();
+            *db = DB::new(&i.db_log, Arc::clone(&log));
+        }
+        let saved = match db {
+            Some(conn) => {
+                let params: Vec<Vec<Value>> = batch.iter().map(|r| r.to_params()).collect();
+                conn.exec_batch(AUDIT_SQL, params)
+            },
+            None => false,
+        };
+        if saved {
+            batch.clear();
+            return true;
+        }
+        *db = None;
+        if batch.len() > AUDIT_MAX {
+            let lost = batch.len() - AUDIT_MAX;
+            batch.drain(..lost);
+            let l = RwLock::read(&log).unwrap();
+            l.write(Error::AuditOverflow, &lost.to_string());
+        }
+        false
+    }
+}
+
+// Файл access.log, відкритий потоком журналу завантажень
+struct AuditFile {
+    name: String,
+    file: Option<File>,
+}
+
+impl AuditFile {
+    // Після помилки файл закривається і відкривається знову з наступним записом
+    fn write(&mut self, record: &AuditRecord, log: Arc<RwLock<Log>>) {
+        if self.file.is_none() {
+            match OpenOptions::new().create(true).append(true).open(&self.name) {
+                Ok(file) => self.file = Some(file),
+                Err(err) => {
+                    let l = RwLock::read(&log).unwrap();
+                    l.write(Error::AuditFile, &err.to_string());
+                    return;
+                },
+            }
+        }
+        if let Some(file) = &mut self.file {
+            if let Err(err) = file.write_all(record.to_line().as_bytes()) {
+                self.file = None;
+                let l = RwLock::read(&log).unwrap();
+                l.write(Error::AuditFile, &err.to_string());
+            }
+        }
+    }
+}
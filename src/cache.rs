@@ -2,7 +2,7 @@ use std::{sync::{Mutex, Arc, RwLock}, thread::{JoinHandle, self}, time::Duration
 
 use chrono::Local;
 
-use crate::{go::Go, init::Init, db::DB, log::Log, data::{Auth, World, Targets, Locks, Products, Bg, Store}};
+use crate::{go::Go, init::Init, db::DB, log::Log, data::{Auth, World, Targets, Locks, Products, Bg, Store, Categories}};
 
 pub const MS1000: std::time::Duration = Duration::from_millis(1000);
 pub const M30: i64 = 25;
@@ -14,6 +14,7 @@ pub struct Cache {
     init: Arc<RwLock<Init>>,
     log: Arc<RwLock<Log>>,
     pub load: bool,
+    pub generation: u64,                // Покоління даних, нове після кожного завантаження, 0 - дані ще не завантажені
 
     pub auth: Arc<Mutex<Auth>>,
     pub kurs: Arc<Mutex<f32>>,
@@ -23,6 +24,7 @@ pub struct Cache {
     pub product: Arc<Mutex<Products>>,
     pub stock: Arc<Mutex<Store>>,
     pub bg: Arc<Mutex<Bg>>,
+    pub category: Arc<Mutex<Categories>>,
 }
 
 impl Cache {
@@ -36,6 +38,7 @@ impl Cache {
             init,
             log,
             load: false,
+            generation: 0,
             
             auth: Arc::new(Mutex::new(Auth::new(i.auth_company_capacity, i.auth_user_capacity))),
             kurs: Arc::new(Mutex::new(0.0)),
@@ -45,12 +48,20 @@ impl Cache {
             product: Arc::new(Mutex::new(Products::new(i.product_capacity))),
             stock: Arc::new(Mutex::new(Store::new(i.stock_capacity, i.stock_product_capacity))),
             bg: Arc::new(Mutex::new(Bg::new(i.bonus_company_capacity, i.bonus_group_capacity))),
+            category: Arc::new(Mutex::new(Categories::new(i.category_capacity))),
         }));
 
         Cache::start(Arc::clone(&cache));
         cache
     }
 
+    // Покоління - час завантаження в мс, тому файли кеша та курсори попереднього запуску сервера
+    // не збігаються з новими даними. Монотонне навіть при переведенні годинника назад
+    pub fn next_generation(generation: u64) -> u64 {
+        let now = Local::now().timestamp_millis().max(0) as u64;
+        now.max(generation + 1)
+    }
+
     fn start(cache: Arc<Mutex<Cache>>) {
         let cache_thread = Arc::clone(&cache);
         let mut load = true;
@@ -61,10 +72,6 @@ impl Cache {
             if Cache::stop(Arc::clone(&cache_thread)) { break; }
 
             if load {
-                if cfg!(debug_assertions) {
-                    println!("{} Start load cache", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
-                }
-
                 let init;
                 let log;
                 {
@@ -73,6 +80,8 @@ impl Cache {
                     log = Arc::clone(&c.log);
                 }
                 let init = RwLock::read(&init).unwrap();
+                let log_read = RwLock::read(&log).unwrap();
+                let span = log_read.span("load cache", None);
                 let mut db_b2b = match DB::new(&init.db_b2b, Arc::clone(&log)) {
                     Some(db) => db,
                     None => {
@@ -144,9 +153,18 @@ impl Cache {
                     }
                     thread::sleep(MS1000);
                 }
-                if cfg!(debug_assertions) {
-                    println!("{} Finish load cache", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
+                loop {
+                    let res = Cache::load_category(Arc::clone(&cache_thread), &mut db_b2b);
+                    if res {
+                        break;
+                    }
+                    thread::sleep(MS1000);
+                }
+                {
+                    let mut c = Mutex::lock(&cache_thread).unwrap();
+                    c.generation = Cache::next_generation(c.generation);
                 }
+                drop(span);
                 last = Local::now();
             } else {
                 thread::sleep(MS1000);
@@ -546,5 +564,41 @@ impl Cache {
         }
     }
 
+    fn load_category(cache: Arc<Mutex<Cache>>, db: &mut DB) -> bool {
+        let category;
+        {
+            let c = Mutex::lock(&cache).unwrap();
+            category = Arc::clone(&c.category);
+        }
+        let sql = "
+            SELECT categoryid, parent, name_ua, name_ru FROM SC_categories WHERE disabled=0 ORDER BY sort_order
+        ";
+        match db.query(sql) {
+            Some(result) => {
+                let row: Vec<(u32, u32, String, String)> = result;
+                let mut c = Mutex::lock(&category).unwrap();
+                c.update(row);
+                true
+            },
+            None => false,
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generation_unique() {
+        // Перше завантаження після кожного запуску починається з 0
+        let first = Cache::next_generation(0);
+        thread::sleep(Duration::from_millis(2));
+        let restart = Cache::next_generation(0);
+        assert!(first > 0 && restart > first);
+        // Завантаження в межах однієї мілісекунди або після переведення годинника назад
+        let next = Cache::next_generation(restart);
+        assert!(next > restart);
+        assert_eq!(Cache::next_generation(u64::MAX - 1), u64::MAX);
+    }
+}
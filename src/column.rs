@@ -0,0 +1,202 @@
+use std::{fs::read_to_string, path::Path};
+
+use serde_json::Value;
+
+use crate::{log::Log, error::Error, price::PriceItem};
+
+// Каталог, вбудований при збірці, якщо поруч з програмою немає columns.json
+const DEFAULT: &str = include_str!("../columns.json");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    String,
+    Money,
+    Index,
+}
+
+// Додаткова умова показу колонки
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    None,
+    Rozn,                               // Право компанії на роздрібну ціну
+    R3,                                 // Право компанії на інтернет ціну
+    Ean,                                // Параметр ean=1
+}
+
+// Колонка прайса
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,
+    pub source: String,                 // Поле товару, PriceItem::source
+    pub local: bool,
+    pub full: bool,
+    pub short: bool,
+    pub full_uah: bool,
+    pub permission: Permission,
+    pub ua: String,                     // Заголовок колонки
+    pub ru: String,
+    pub decimals: usize,                // Знаків після коми для ціни
+}
+
+// Каталог колонок прайса, файл columns.json. Порядок колонок у файлі - порядок колонок прайса
+//
+// [
+//     {"name": "RetailPrice", "source": "retail_price", "type": "money", "volume": ["full_uah"],
+//      "permission": "rozn", "title": {"ua": "Роздрібна ціна", "ru": "Розничная цена"}, "decimals": 2},
+//     ...
+// ]
+#[derive(Debug)]
+pub struct Columns {
+    pub list: Vec<Column>,
+}
+
+impl Columns {
+    pub fn new(dir: &str, log: &Log) -> Columns {
+        let file_name = format!("{}/columns.json", dir);
+        let text = if Path::new(&file_name).exists() {
+            match read_to_string(&file_name) {
+                Ok(text) => text,
+                Err(err) => log.exit(Error::ColumnsFormat, &err.to_string()),
+            }
+        } else {
+            DEFAULT.to_owned()
+        };
+        let val: Value = match serde_json::from_str(&text) {
+            Ok(val) => val,
+            Err(err) => log.exit(Error::ColumnsFormat, &err.to_string()),
+        };
+        let items = match val.as_array() {
+            Some(items) if items.len() > 0 => items,
+            _ => log.exit(Error::ColumnsFormat, ""),
+        };
+        let mut list = Vec::with_capacity(items.len());
+        for (i, item) in items.iter().enumerate() {
+            let col = match Columns::column(item) {
+                Ok(col) => col,
+                Err(field) => log.exit(Error::ColumnsFormat, &format!("[{}].{}", i, field)),
+            };
+            if list.iter().any(|c| Columns::clash(c, &col)) {
+                log.exit(Error::ColumnsFormat, &format!("[{}].name", i));
+            }
+            list.push(col);
+        }
+        Columns { list }
+    }
+
+    // Однакові назви в одному обсязі дали б повтор елемента XML та ключа JSON.
+    // Колонка з правом компанії може потрапити в будь-який обсяг
+    fn clash(a: &Column, b: &Column) -> bool {
+        a.name == b.name && (
+            (a.local && b.local) || (a.full && b.full) || (a.short && b.short) || (a.full_uah && b.full_uah)
+            || a.permission != Permission::None || b.permission != Permission::None
+        )
+    }
+
+    // Назва колонки - ім'я елемента XML: [A-Za-z_][A-Za-z0-9_.-]*
+    fn ncname(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {},
+            _ => return false,
+        }
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+    }
+
+    // Помилка - назва невірного поля
+    fn column(item: &Value) -> Result<Column, &'static str> {
+        let name = match item.get("name").and_then(|n| n.as_str()) {
+            Some(name) if Columns::ncname(name) => name.to_owned(),
+            _ => return Err("name"),
+        };
+        let source = match item.get("source").and_then(|s| s.as_str()) {
+            Some(source) => source.to_owned(),
+            None => return Err("source"),
+        };
+        let kind = match item.get("type").and_then(|t| t.as_str()) {
+            Some("string") => Kind::String,
+            Some("money") => Kind::Money,
+            Some("index") => Kind::Index,
+            _ => return Err("type"),
+        };
+        // Тип колонки має збігатися з типом поля товару
+        match PriceItem::source(&source) {
+            Some((k, _)) if k == kind => {},
+            Some(_) => return Err("type"),
+            None => return Err("source"),
+        }
+        let (mut local, mut full, mut short, mut full_uah) = (false, false, false, false);
+        match item.get("volume").and_then(|v| v.as_array()) {
+            Some(volume) => for v in volume {
+                match v.as_str() {
+                    Some("local") => local = true,
+                    Some("full") => full = true,
+                    Some("short") => short = true,
+                    Some("full_uah") => full_uah = true,
+                    _ => return Err("volume"),
+                }
+            },
+            None => return Err("volume"),
+        }
+        let permission = match item.get("permission").map(|p| p.as_str()) {
+            None => Permission::None,
+            Some(Some("rozn")) => Permission::Rozn,
+            Some(Some("r3")) => Permission::R3,
+            Some(Some("ean")) => Permission::Ean,
+            Some(_) => return Err("permission"),
+        };
+        let title = item.get("title");
+        let ua = match title.and_then(|t| t.get("ua")).map(|t| t.as_str()) {
+            Some(Some(ua)) => ua.to_owned(),
+            Some(None) => return Err("title.ua"),
+            None => name.clone(),
+        };
+        let ru = match title.and_then(|t| t.get("ru")).map(|t| t.as_str()) {
+            Some(Some(ru)) => ru.to_owned(),
+            Some(None) => return Err("title.ru"),
+            None => ua.clone(),
+        };
+        let decimals = match item.get("decimals") {
+            Some(decimals) => match decimals.as_u64() {
+                Some(decimals) if decimals <= 6 => decimals as usize,
+                _ => return Err("decimals"),
+            },
+            None => 2,
+        };
+        Ok(Column { name, source, local, full, short, full_uah, permission, ua, ru, decimals })
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.list.iter().any(|col| col.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for name in ["Code", "_id", "Price.USD", "price-2"] {
+            assert!(Columns::ncname(name), "{}", name);
+        }
+        for name in ["", "2Code", "-x", "Назва", "Price USD", "a:b", "<x>"] {
+            assert!(!Columns::ncname(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn default_catalogue() {
+        let val: Value = serde_json::from_str(DEFAULT).unwrap();
+        let mut list: Vec<Column> = Vec::new();
+        for item in val.as_array().unwrap() {
+            let col = Columns::column(item).unwrap();
+            assert!(!list.iter().any(|c| Columns::clash(c, &col)), "{}", col.name);
+            list.push(col);
+        }
+        // Available у short стоїть в іншому місці, обсяги не перетинаються
+        let short = list.iter().find(|c| c.name == "Available" && c.short).unwrap();
+        let mut same = short.clone();
+        same.full = true;
+        assert!(list.iter().any(|c| Columns::clash(c, &same)));
+    }
+}
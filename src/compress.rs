@@ -0,0 +1,187 @@
+use std::{fs::{read, write, rename, remove_file}, io::Write};
+
+use flate2::{write::GzEncoder, Compression};
+
+use crate::meta::PriceMeta;
+
+// Стиснення відповіді, узгоджується з заголовком Accept-Encoding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Identity,
+    Gzip,
+    Zstd,
+    Brotli,
+}
+
+impl Encoding {
+    // Обирається кодування з найбільшим q, при рівних q перевага: br, zstd, gzip.
+    // "*" задає q для кодувань, не названих явно, варіант з q=0 заборонений клієнтом
+    pub fn negotiate(accept: Option<&String>) -> Encoding {
+        let accept = match accept {
+            Some(accept) => accept,
+            None => return Encoding::Identity,
+        };
+        let mut gzip: Option<f32> = None;
+        let mut zstd: Option<f32> = None;
+        let mut brotli: Option<f32> = None;
+        let mut any: Option<f32> = None;
+        for item in accept.split(',') {
+            let mut parts = item.split(';');
+            let name = parts.next().unwrap_or("").trim().to_lowercase();
+            let mut q = 1.0;
+            for p in parts {
+                let p = p.trim();
+                if let Some(val) = p.strip_prefix("q=") {
+                    q = match val.trim().parse::<f32>() {
+                        Ok(val) if val >= 0.0 && val <= 1.0 => val,
+                        _ => 0.0,
+                    };
+                }
+            }
+            match name.as_str() {
+                "gzip" | "x-gzip" => gzip = Some(q),
+                "zstd" => zstd = Some(q),
+                "br" => brotli = Some(q),
+                "*" => any = Some(q),
+                _ => {},
+            }
+        }
+        let mut res = Encoding::Identity;
+        let mut best = 0.0;
+        for (encoding, q) in [(Encoding::Brotli, brotli), (Encoding::Zstd, zstd), (Encoding::Gzip, gzip)] {
+            let q = q.or(any).unwrap_or(0.0);
+            if q > best {
+                res = encoding;
+                best = q;
+            }
+        }
+        res
+    }
+
+    // Значення заголовка Content-Encoding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Identity => "identity",
+            Encoding::Gzip => "gzip",
+            Encoding::Zstd => "zstd",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    // Розширення стисненого файлу поруч з файлом кеша
+    pub fn ext(&self) -> &'static str {
+        match self {
+            Encoding::Identity => "",
+            Encoding::Gzip => "gz",
+            Encoding::Zstd => "zst",
+            Encoding::Brotli => "br",
+        }
+    }
+
+    pub fn file(&self, file: &str) -> String {
+        format!("{}.{}", file, self.ext())
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Identity => Some(data.to_vec()),
+            Encoding::Gzip => {
+                let mut enc = GzEncoder::new(Vec::with_capacity(data.len() / 4), Compression::default());
+                if let Err(_) = enc.write_all(data) {
+                    return None;
+                }
+                match enc.finish() {
+                    Ok(res) => Some(res),
+                    Err(_) => None,
+                }
+            },
+            Encoding::Zstd => match zstd::stream::encode_all(data, 3) {
+                Ok(res) => Some(res),
+                Err(_) => None,
+            },
+            Encoding::Brotli => {
+                let mut res = Vec::with_capacity(data.len() / 4);
+                {
+                    let mut enc = brotli::CompressorWriter::new(&mut res, 4096, 9, 22);
+                    if let Err(_) = enc.write_all(data) {
+                        return None;
+                    }
+                }
+                Some(res)
+            },
+        }
+    }
+
+    // Стиснений варіант файлу кеша, створюється один раз на генерацію.
+    // etag - метадані, з якими прочитано data. Поки запит стискав старий вміст, власник генерації
+    // міг видалити файл і записати новий, тому варіант зберігається лише для поточних метаданих
+    pub fn cached(&self, file: &str, data: &[u8], etag: Option<&str>) -> Option<Vec<u8>> {
+        let name = self.file(file);
+        if let Ok(res) = read(&name) {
+            return Some(res);
+        }
+        let res = self.compress(data)?;
+        let etag = match etag {
+            Some(etag) => etag,
+            None => return Some(res),
+        };
+        let current = || PriceMeta::read(file).map_or(false, |meta| meta.etag == etag);
+        let tmp = format!("{}.tmp", name);
+        if let Err(_) = write(&tmp, &res) {
+            return Some(res);
+        }
+        if !current() || rename(&tmp, &name).is_err() {
+            if let Err(_) = remove_file(&tmp) { }
+            return Some(res);
+        }
+        // Файл могли перегенерувати між перевіркою та перейменуванням
+        if !current() {
+            if let Err(_) = remove_file(&name) { }
+        }
+        Some(res)
+    }
+
+    pub const ALL: [Encoding; 3] = [Encoding::Gzip, Encoding::Zstd, Encoding::Brotli];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate(accept: &str) -> Encoding {
+        Encoding::negotiate(Some(&accept.to_owned()))
+    }
+
+    #[test]
+    fn negotiate_q() {
+        assert_eq!(Encoding::negotiate(None), Encoding::Identity);
+        assert_eq!(negotiate("gzip, deflate, br, zstd"), Encoding::Brotli);
+        assert_eq!(negotiate("gzip;q=1, br;q=0.1"), Encoding::Gzip);
+        assert_eq!(negotiate("br;q=0, gzip"), Encoding::Gzip);
+        assert_eq!(negotiate("gzip;q=0.5, zstd;q=0.5"), Encoding::Zstd);
+        assert_eq!(negotiate("deflate"), Encoding::Identity);
+    }
+
+    #[test]
+    fn negotiate_any() {
+        assert_eq!(negotiate("*"), Encoding::Brotli);
+        assert_eq!(negotiate("br;q=0, *;q=0.5"), Encoding::Zstd);
+        assert_eq!(negotiate("gzip, *;q=0.1"), Encoding::Gzip);
+        assert_eq!(negotiate("*;q=0"), Encoding::Identity);
+    }
+
+    #[test]
+    fn cached_current_meta() {
+        let file = std::env::temp_dir().join(format!("price_test_{}.json", std::process::id()));
+        let file = file.to_str().unwrap();
+        let meta = PriceMeta { key: "key".to_owned(), created: 0, generation: 1, ttl: 60, etag: PriceMeta::etag(b"new"), rows: None };
+        assert!(meta.write(file));
+        // Старий вміст, прочитаний до перегенерації, не зберігається
+        let old = PriceMeta::etag(b"old");
+        assert!(Encoding::Gzip.cached(file, b"old", Some(&old)).is_some());
+        assert!(!std::path::Path::new(&Encoding::Gzip.file(file)).exists());
+        assert!(Encoding::Gzip.cached(file, b"new", Some(&meta.etag)).is_some());
+        assert!(std::path::Path::new(&Encoding::Gzip.file(file)).exists());
+        assert!(PriceMeta::remove(file));
+    }
+}
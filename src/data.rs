@@ -1,4 +1,4 @@
-use std::{collections::{HashMap, hash_map::Entry}};
+use std::{collections::{HashMap, HashSet, hash_map::Entry}};
 
 #[derive(Debug)]
 pub struct Store {
@@ -572,6 +572,79 @@ impl Target {
     }
 }
 
+// Дерево категорій для впорядкування прайса та розділу категорій
+#[derive(Debug)]
+pub struct Categories {
+    order: HashMap<u32, u32>,           // Позиція категорії при обході дерева в глибину
+    pub list: Vec<Category>,            // Категорії в порядку обходу дерева
+}
+
+impl Categories {
+    pub fn new(cap: usize) -> Categories {
+        Categories {
+            order: HashMap::with_capacity(cap),
+            list: Vec::with_capacity(cap),
+        }
+    }
+
+    // list - (categoryID, parent, name_ua, name_ru) впорядковані за sort_order, коренева категорія 1
+    pub fn update(&mut self, list: Vec<(u32, u32, String, String)>) {
+        let mut children: HashMap<u32, Vec<usize>> = HashMap::with_capacity(list.len());
+        for (i, (_, parent_id, _, _)) in list.iter().enumerate() {
+            children.entry(*parent_id).or_default().push(i);
+        }
+        self.order.clear();
+        self.list.clear();
+        let mut stack: Vec<(usize, usize)> = Vec::with_capacity(64);
+        if let Some(root) = children.get(&1) {
+            stack.extend(root.iter().rev().map(|i| (*i, 0)));
+        }
+        while let Some((i, depth)) = stack.pop() {
+            let (category_id, parent_id, ua, ru) = &list[i];
+            if self.order.contains_key(category_id) {
+                continue;
+            }
+            self.order.insert(*category_id, self.list.len() as u32);
+            self.list.push(Category { id: *category_id, parent: *parent_id, depth, ua: ua.clone(), ru: ru.clone() });
+            if let Some(sub) = children.get(category_id) {
+                stack.extend(sub.iter().rev().map(|i| (*i, depth + 1)));
+            }
+        }
+    }
+
+    // Категорії разом з усіма підкатегоріями. Категорія поза деревом додається без підкатегорій
+    pub fn subtree(&self, ids: &[u32]) -> HashSet<u32> {
+        let mut res = HashSet::with_capacity(ids.len() * 8);
+        for id in ids {
+            res.insert(*id);
+            if let Some(&pos) = self.order.get(id) {
+                let depth = self.list[pos as usize].depth;
+                for c in self.list[pos as usize + 1..].iter().take_while(|c| c.depth > depth) {
+                    res.insert(c.id);
+                }
+            }
+        }
+        res
+    }
+
+    // Категорії поза деревом в кінці прайса
+    pub fn order(&self, category_id: u32) -> u32 {
+        match self.order.get(&category_id) {
+            Some(pos) => *pos,
+            None => u32::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Category {
+    pub id: u32,
+    pub parent: u32,                    // 1 - коренева категорія
+    pub depth: usize,                   // 0 - категорія верхнього рівня
+    pub ua: String,
+    pub ru: String,
+}
+
 #[derive(Debug)]
 pub struct World {
     pub countries: HashMap<u32, Country>,
@@ -718,4 +791,48 @@ impl User {
         self.rozn = rozn;
         self.r3 = r3;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 - корінь
+    //   10
+    //     11
+    //       111
+    //     12
+    //   20
+    //     21
+    fn tree() -> Categories {
+        let mut c = Categories::new(8);
+        let list = [(10, 1), (11, 10), (111, 11), (12, 10), (20, 1), (21, 20), (30, 99)];
+        c.update(list.iter().map(|&(id, parent)| (id, parent, id.to_string(), id.to_string())).collect());
+        c
+    }
+
+    fn subtree(c: &Categories, ids: &[u32]) -> Vec<u32> {
+        let mut res: Vec<u32> = c.subtree(ids).into_iter().collect();
+        res.sort();
+        res
+    }
+
+    #[test]
+    fn tree_order() {
+        let c = tree();
+        let list: Vec<(u32, usize)> = c.list.iter().map(|c| (c.id, c.depth)).collect();
+        assert_eq!(list, vec![(10, 0), (11, 1), (111, 2), (12, 1), (20, 0), (21, 1)]);
+        assert_eq!(c.order(30), u32::MAX);
+    }
+
+    #[test]
+    fn tree_subtree() {
+        let c = tree();
+        assert_eq!(subtree(&c, &[10]), vec![10, 11, 12, 111]);
+        assert_eq!(subtree(&c, &[11]), vec![11, 111]);
+        assert_eq!(subtree(&c, &[12, 21]), vec![12, 21]);
+        assert_eq!(subtree(&c, &[20]), vec![20, 21]);
+        // Категорія поза деревом без підкатегорій
+        assert_eq!(subtree(&c, &[30]), vec![30]);
+    }
+}
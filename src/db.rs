@@ -1,8 +1,8 @@
 use std::sync::{Arc, RwLock};
 
-use mysql::{Opts, Conn, prelude::{Queryable, FromRow}};
+use mysql::{Opts, Conn, Value, prelude::{Queryable, FromRow}};
 
-use crate::{init::DBInit, log::Log};
+use crate::{init::DBInit, log::Log, error::Error};
 
 pub struct DB {
     conn: Conn,
@@ -16,27 +16,27 @@ impl DB {
         let opts = match Opts::try_from(url) {
             Ok(opts) => opts,
             Err(err) => {
-                log_read.write(600, &format!("{}. Err: {}", url, err.to_string()));
+                log_read.write(Error::DbUrl, &format!("{}. Err: {}", url, err.to_string()));
                 return None;
             },
         };
         let mut conn = match Conn::new(opts) {
             Ok(conn) => conn,
             Err(err) => {
-                log_read.write(601, &format!("{}. Err: {}", url, err.to_string()));
+                log_read.write(Error::DbConnect, &format!("{}. Err: {}", url, err.to_string()));
                 return None;
             },
         };
         if let Err(err) = conn.query_drop("SET NAMES 'utf8'") {
-            log_read.write(603, &err.to_string());
+            log_read.write(Error::DbSetup, &err.to_string());
             return None;
         }
         if let Err(err) = conn.query_drop("SET CHARACTER SET 'utf8'") {
-            log_read.write(603, &err.to_string());
+            log_read.write(Error::DbSetup, &err.to_string());
             return None;
         }
         if let Err(err) = conn.query_drop("SET SESSION collation_connection = 'utf8_general_ci'") {
-            log_read.write(603, &err.to_string());
+            log_read.write(Error::DbSetup, &err.to_string());
             return None;
         }
 
@@ -82,11 +82,24 @@ impl DB {
                 let result = format!("sql: {}\nErr: {}", text, e.to_string());
                 if let Some(l) = &self.log {
                     let log = RwLock::read(l).unwrap();
-                    log.write(602, &result);
+                    log.write(Error::DbQuery, &result);
                 }
                 None
             },
         };
         result
     }
+
+    pub fn exec_batch(&mut self, text: &str, params: Vec<Vec<Value>>) -> bool {
+        match self.conn.exec_batch(text, params) {
+            Ok(()) => true,
+            Err(e) => {
+                if let Some(l) = &self.log {
+                    let log = RwLock::read(l).unwrap();
+                    log.write(Error::DbQuery, &format!("sql: {}\nErr: {}", text, e.to_string()));
+                }
+                false
+            },
+        }
+    }
 }
@@ -0,0 +1,413 @@
+use serde_json::Value;
+
+// Важливість помилки
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Fatal,                              // Зупинка програми
+    Error,                              // Помилка сервера, робота продовжується
+    Warn,                               // Попередження
+    Client,                             // Помилка в запиті клієнта
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Fatal => "fatal",
+            Severity::Error => "error",
+            Severity::Warn => "warn",
+            Severity::Client => "client",
+        }
+    }
+}
+
+// Каталог кодів помилок
+//
+// 1..99    - помилки запиту прайса, повертаються клієнту
+// 100..199 - файл конфігурації та початкове завантаження
+// 200..299 - керування сервером через IRC
+// 300..399 - сокет IRC
+// 400..499 - сокет TCP
+// 500..599 - робочі потоки та кеш прайсів
+// 600..699 - база даних
+// 700..799 - журнал завантажень
+//
+// Enum Error та Error::ALL будуються макросом з одного переліку,
+// тож нова помилка не може бути пропущена в каталозі
+macro_rules! errors {
+    ($($name:ident,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum Error {
+            $($name,)*
+        }
+
+        impl Error {
+            pub const ALL: &'static [Error] = &[$(Error::$name,)*];
+        }
+    };
+}
+
+errors! {
+    FormatMissing,
+    FormatUnsupported,
+    FullMissing,
+    FullUnsupported,
+    CompanyMissing,
+    CompanyFormat,
+    TargetMissing,
+    TargetFormat,
+    LangMissing,
+    LangFormat,
+    TimeMissing,
+    TimeFormat,
+    UserMissing,
+    UserFormat,
+    TokenMissing,
+    TokenInvalid,
+    CompanyNotFound,
+    UserNotFound,
+    ProfileNotSet,
+    CacheCheck,
+    CacheExpired,
+    CacheRemove,
+    TargetZero,
+    ServerStopped,
+    TargetUnknown,
+    TargetNoStore,
+    PriceQuery,
+    PriceRead,
+    XlsxCreate,
+    PhpCreate,
+    XmlCreate,
+    JsonCreate,
+    RateLimit,
+    Quota,
+    SortUnsupported,
+    CsvOptionUnsupported,
+    CsvCreate,
+    YmlCreate,
+    GmcCreate,
+    CmlCreate,
+    NdjsonCreate,
+    PageUnsupported,
+    CursorExpired,
+    FilterFormat,
+    LookupUnsupported,
+    FieldsUnsupported,
+    FieldsForbidden,
+
+    ConfigRead,
+    ConfigParse,
+    PortMissing,
+    PortFormat,
+    TimeZoneMissing,
+    TimeZoneFormat,
+    MaxMissing,
+    MaxFormat,
+    DbLogHostMissing,
+    DbLogHostFormat,
+    DbLogPortMissing,
+    DbLogPortFormat,
+    DbLogUserMissing,
+    DbLogUserFormat,
+    DbLogPwdMissing,
+    DbLogPwdFormat,
+    DbLogNameMissing,
+    DbLogNameFormat,
+    DbB2bHostMissing,
+    DbB2bHostFormat,
+    DbB2bPortMissing,
+    DbB2bPortFormat,
+    DbB2bUserMissing,
+    DbB2bUserFormat,
+    DbB2bPwdMissing,
+    DbB2bPwdFormat,
+    DbB2bNameMissing,
+    DbB2bNameFormat,
+    DbLocalHostMissing,
+    DbLocalHostFormat,
+    DbLocalPortMissing,
+    DbLocalPortFormat,
+    DbLocalUserMissing,
+    DbLocalUserFormat,
+    DbLocalPwdMissing,
+    DbLocalPwdFormat,
+    DbLocalNameMissing,
+    DbLocalNameFormat,
+    IrcMissing,
+    IrcFormat,
+    SaltMissing,
+    SaltFormat,
+    LimitCompanyHourFormat,
+    LimitUserHourFormat,
+    LimitBurstFormat,
+    QuotaCompanyDayFormat,
+    QuotaUserDayFormat,
+    AuditFileFormat,
+    LogLevelFormat,
+    LogSinkFormat,
+    LogSyslogFormat,
+    LogMaxSizeFormat,
+    LogDailyFormat,
+    LogKeepFormat,
+    MessagesFormat,
+    CacheMaxFormat,
+    PriceTtlFormat,
+    WarmupFormat,
+    WarmupTopFormat,
+    WarmupPauseFormat,
+    ColumnsFormat,
+
+    InitB2bConnect,
+    InitB2bQuery,
+    InitLogConnect,
+    InitLogQuery,
+
+    ServerStart,
+    IrcConnect,
+    IrcTimeout,
+    IrcSend,
+    IrcReadTimeout,
+    IrcRead,
+    IrcEmpty,
+    IrcData,
+    IrcPid,
+
+    IrcPermission,
+    IrcInUse,
+    IrcNotAvailable,
+    IrcBind,
+    IrcNonBlocking,
+
+    TcpPermission,
+    TcpInUse,
+    TcpNotAvailable,
+    TcpBind,
+    TcpNonBlocking,
+
+    QueueTake,
+    WorkerNotFound,
+    CacheNotDir,
+    CacheCreate,
+    WorkerChannel,
+
+    DbUrl,
+    DbConnect,
+    DbQuery,
+    DbSetup,
+
+    AuditFile,
+    AuditOverflow,
+    AuditLost,
+}
+
+impl Error {
+    pub fn code(&self) -> u32 {
+        self.spec().0
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.spec().1
+    }
+
+    pub fn status(&self) -> u16 {
+        self.spec().2
+    }
+
+    pub fn message(&self) -> &'static str {
+        self.spec().3
+    }
+
+    // Код, важливість, HTTP статус, опис
+    fn spec(&self) -> (u32, Severity, u16, &'static str) {
+        match self {
+            Error::FormatMissing => (1, Severity::Client, 400, "Відсутній параметр format"),
+            Error::FormatUnsupported => (2, Severity::Client, 400, "Формат прайс-листа не підтримується"),
+            Error::FullMissing => (3, Severity::Client, 400, "Відсутній параметр full"),
+            Error::FullUnsupported => (4, Severity::Client, 400, "Тип прайсу не підтримується"),
+            Error::CompanyMissing => (5, Severity::Client, 400, "Відсутній параметр companyID"),
+            Error::CompanyFormat => (6, Severity::Client, 400, "Невірний формат companyID"),
+            Error::TargetMissing => (7, Severity::Client, 400, "Відсутній параметр targetID"),
+            Error::TargetFormat => (8, Severity::Client, 400, "Невірний формат targetID"),
+            Error::LangMissing => (9, Severity::Client, 400, "Відсутній параметр lang"),
+            Error::LangFormat => (10, Severity::Client, 400, "Невірний формат lang"),
+            Error::TimeMissing => (11, Severity::Client, 400, "Відсутній параметр time"),
+            Error::TimeFormat => (12, Severity::Client, 400, "Невірний формат time"),
+            Error::UserMissing => (13, Severity::Client, 400, "Відсутній параметр userID"),
+            Error::UserFormat => (14, Severity::Client, 400, "Невірний формат userID"),
+            Error::TokenMissing => (15, Severity::Client, 401, "Відсутній параметр token"),
+            Error::TokenInvalid => (16, Severity::Client, 401, "Невірний token"),
+            Error::CompanyNotFound => (17, Severity::Client, 403, "Доступ заборонено (companyID не знайдено)"),
+            Error::UserNotFound => (18, Severity::Client, 403, "Доступ заборонено (userID не знайдено)"),
+            Error::ProfileNotSet => (19, Severity::Client, 403, "Доступ заборонено (profilesID не встановлено)"),
+            Error::CacheCheck => (20, Severity::Error, 500, "Неможливо перевірити директорію cache для прайсів"),
+            Error::CacheExpired => (21, Severity::Error, 500, "Неможливо видалити застарілі cache-файли для прайсів"),
+            Error::CacheRemove => (22, Severity::Error, 500, "Неможливо видалити cache-файли для прайсів"),
+            Error::TargetZero => (23, Severity::Client, 400, "targetID повинен бути > 0"),
+            Error::ServerStopped => (24, Severity::Warn, 503, "Сервер прайсів зупинено"),
+            Error::TargetUnknown => (25, Severity::Client, 404, "Невідомий targetID"),
+            Error::TargetNoStore => (26, Severity::Client, 404, "До targetID не прив'язан склад"),
+            Error::PriceQuery => (27, Severity::Error, 503, "Неможливо отримати ціни"),
+            Error::PriceRead => (28, Severity::Error, 500, "Неможливо прочитати ціни"),
+            Error::XlsxCreate => (29, Severity::Error, 500, "Неможливо створити xlsx файл"),
+            Error::PhpCreate => (30, Severity::Error, 500, "Неможливо створити php файл"),
+            Error::XmlCreate => (31, Severity::Error, 500, "Неможливо створити xml файл"),
+            Error::JsonCreate => (32, Severity::Error, 500, "Неможливо створити json файл"),
+            Error::RateLimit => (33, Severity::Client, 429, "Перевищено ліміт запитів, спробуйте пізніше"),
+            Error::Quota => (34, Severity::Client, 429, "Перевищено денну квоту генерації прайсів"),
+            Error::SortUnsupported => (35, Severity::Client, 400, "Порядок сортування не підтримується (category, code, price)"),
+            Error::CsvOptionUnsupported => (36, Severity::Client, 400, "Невірний параметр CSV (delimiter: comma, semicolon, pipe; quote: minimal, all; decimal: comma, point; decimal=comma не поєднується з delimiter=comma)"),
+            Error::CsvCreate => (37, Severity::Error, 500, "Неможливо створити csv файл"),
+            Error::YmlCreate => (38, Severity::Error, 500, "Неможливо створити yml файл"),
+            Error::GmcCreate => (39, Severity::Error, 500, "Неможливо створити фід Google Merchant Center"),
+            Error::CmlCreate => (40, Severity::Error, 500, "Неможливо створити файл CommerceML"),
+            Error::NdjsonCreate => (41, Severity::Error, 500, "Неможливо створити ndjson файл"),
+            Error::PageUnsupported => (42, Severity::Client, 400, "Невірні параметри сторінки (лише format=json; limit 1-10000; offset або cursor)"),
+            Error::CursorExpired => (43, Severity::Client, 410, "Курсор застарів, дані прайса оновились, почніть з першої сторінки"),
+            Error::FilterFormat => (44, Severity::Client, 400, "Невірний фільтр (category, vendor, group, class - списки через кому; stock=1; delivery - днів; price_min, price_max - ціна)"),
+            Error::LookupUnsupported => (45, Severity::Client, 400, "Невірний запит товарів (product, code - до 1000 кодів через кому; лише format=json або xml, без сторінок)"),
+            Error::FieldsUnsupported => (46, Severity::Client, 400, "Невідома колонка в параметрі fields"),
+            Error::FieldsForbidden => (47, Severity::Client, 403, "Колонка з параметра fields недоступна компанії"),
+
+            Error::ConfigRead => (100, Severity::Fatal, 500, "Відсутній файл конфігурації"),
+            Error::ConfigParse => (101, Severity::Fatal, 500, "Файл конфігурації має невірний формат"),
+            Error::PortMissing => (102, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'port' (Порт TCP запуску сервера генерації прайсів B2B)"),
+            Error::PortFormat => (103, Severity::Fatal, 500, "В файлі конфігурації параметр 'port' має невірний формат (Число від 1 до 65536)"),
+            Error::TimeZoneMissing => (104, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'time_zone'"),
+            Error::TimeZoneFormat => (105, Severity::Fatal, 500, "В файлі конфігурації параметр 'time_zone' має невірний формат (https://en.wikipedia.org/wiki/List_of_tz_database_time_zones)"),
+            Error::MaxMissing => (106, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'max' (Кількість потоків на обробку прайсів)"),
+            Error::MaxFormat => (107, Severity::Fatal, 500, "В файлі конфігурації параметр 'max' має невірний формат (Число від 1 до 255)"),
+            Error::DbLogHostMissing => (108, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_log_host' (Хост бази даних MySql логістики товарів)"),
+            Error::DbLogHostFormat => (109, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_log_host' має невірний формат"),
+            Error::DbLogPortMissing => (110, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_log_port' (Порт бази даних MySql логістики товарів)"),
+            Error::DbLogPortFormat => (111, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_log_port' має невірний формат (Число від 1 до 65536)"),
+            Error::DbLogUserMissing => (112, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_log_user' (Користувач бази даних MySql логістики товарів)"),
+            Error::DbLogUserFormat => (113, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_log_user' має невірний формат"),
+            Error::DbLogPwdMissing => (114, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_log_pwd' (Пароль користувача бази даних MySql логістики товарів)"),
+            Error::DbLogPwdFormat => (115, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_log_pwd' має невірний формат"),
+            Error::DbLogNameMissing => (116, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_log_name' (Назва бази даних MySql логістики товарів)"),
+            Error::DbLogNameFormat => (117, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_log_name' має невірний формат"),
+            Error::DbB2bHostMissing => (118, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_b2b_host' (Хост бази даних MySql B2B портала)"),
+            Error::DbB2bHostFormat => (119, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_b2b_host' має невірний формат"),
+            Error::DbB2bPortMissing => (120, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_b2b_port' (Порт бази даних MySql B2B портала)"),
+            Error::DbB2bPortFormat => (121, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_b2b_port' має невірний формат (Число від 1 до 65536)"),
+            Error::DbB2bUserMissing => (122, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_b2b_user' (Користувач бази даних MySql B2B портала)"),
+            Error::DbB2bUserFormat => (123, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_b2b_user' має невірний формат"),
+            Error::DbB2bPwdMissing => (124, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_b2b_pwd' (Пароль користувача бази даних MySql B2B портала)"),
+            Error::DbB2bPwdFormat => (125, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_b2b_pwd' має невірний формат"),
+            Error::DbB2bNameMissing => (126, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_b2b_name' (Назва бази даних MySql B2B портала)"),
+            Error::DbB2bNameFormat => (127, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_b2b_name' має невірний формат"),
+            Error::DbLocalHostMissing => (128, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_local_host' (Хост бази даних MySql логіювання запитів)"),
+            Error::DbLocalHostFormat => (129, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_local_host' має невірний формат"),
+            Error::DbLocalPortMissing => (130, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_local_port' (Порт бази даних MySql логіювання запитів)"),
+            Error::DbLocalPortFormat => (131, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_local_port' має невірний формат (Число від 1 до 65536)"),
+            Error::DbLocalUserMissing => (132, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_local_user' (Користувач бази даних MySql логіювання запитів)"),
+            Error::DbLocalUserFormat => (133, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_local_user' має невірний формат"),
+            Error::DbLocalPwdMissing => (134, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_local_pwd' (Пароль користувача бази даних MySql логіювання запитів)"),
+            Error::DbLocalPwdFormat => (135, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_local_pwd' має невірний формат"),
+            Error::DbLocalNameMissing => (136, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'db_local_name' (Назва бази даних MySql логіювання запитів)"),
+            Error::DbLocalNameFormat => (137, Severity::Fatal, 500, "В файлі конфігурації параметр 'db_local_name' має невірний формат"),
+            Error::IrcMissing => (138, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'irc' (Порт TCP управління сервера генерації прайсів B2B)"),
+            Error::IrcFormat => (139, Severity::Fatal, 500, "В файлі конфігурації параметр 'irc' має невірний формат (Число від 1 до 65536)"),
+            Error::SaltMissing => (140, Severity::Fatal, 500, "В файлі конфігурації відсутній параметр 'salt'"),
+            Error::SaltFormat => (141, Severity::Fatal, 500, "В файлі конфігурації параметр 'salt' має невірний формат"),
+            Error::LimitCompanyHourFormat => (142, Severity::Fatal, 500, "В файлі конфігурації параметр 'limit_company_hour' має невірний формат (Число від 0 до 4294967295)"),
+            Error::LimitUserHourFormat => (143, Severity::Fatal, 500, "В файлі конфігурації параметр 'limit_user_hour' має невірний формат (Число від 0 до 4294967295)"),
+            Error::LimitBurstFormat => (144, Severity::Fatal, 500, "В файлі конфігурації параметр 'limit_burst' має невірний формат (Число від 0 до 4294967295)"),
+            Error::QuotaCompanyDayFormat => (145, Severity::Fatal, 500, "В файлі конфігурації параметр 'quota_company_day' має невірний формат (Число від 0 до 4294967295)"),
+            Error::QuotaUserDayFormat => (146, Severity::Fatal, 500, "В файлі конфігурації параметр 'quota_user_day' має невірний формат (Число від 0 до 4294967295)"),
+            Error::AuditFileFormat => (147, Severity::Fatal, 500, "В файлі конфігурації параметр 'audit_file' має невірний формат (true або false)"),
+            Error::LogLevelFormat => (148, Severity::Fatal, 500, "В файлі конфігурації параметр 'log_level' має невірний формат (error, warn, info або debug)"),
+            Error::LogSinkFormat => (149, Severity::Fatal, 500, "В файлі конфігурації параметр 'log_sink' має невірний формат (Масив з file, stderr, syslog)"),
+            Error::LogSyslogFormat => (150, Severity::Fatal, 500, "В файлі конфігурації параметр 'log_syslog' має невірний формат (Шлях до сокета syslog)"),
+            Error::LogMaxSizeFormat => (151, Severity::Fatal, 500, "В файлі конфігурації параметр 'log_max_size' має невірний формат (Розмір в байтах, 0 - без ротації)"),
+            Error::LogDailyFormat => (152, Severity::Fatal, 500, "В файлі конфігурації параметр 'log_daily' має невірний формат (true або false)"),
+            Error::LogKeepFormat => (153, Severity::Fatal, 500, "В файлі конфігурації параметр 'log_keep' має невірний формат (Кількість архівних файлів)"),
+            Error::MessagesFormat => (154, Severity::Fatal, 500, "Файл перекладів повідомлень errors.json має невірний формат"),
+            Error::CacheMaxFormat => (155, Severity::Fatal, 500, "В файлі конфігурації параметр 'cache_max_mb' має невірний формат (Розмір кеша прайсів в МБ, 0 - без обмеження)"),
+            Error::PriceTtlFormat => (156, Severity::Fatal, 500, "В файлі конфігурації параметр 'price_ttl' має невірний формат (Час життя прайса в хвилинах: default, формат, обсяг або формат_обсяг)"),
+            Error::WarmupFormat => (157, Severity::Fatal, 500, "В файлі конфігурації параметр 'warmup' має невірний формат (Список рядків запиту прайса без time та token)"),
+            Error::WarmupTopFormat => (158, Severity::Fatal, 500, "В файлі конфігурації параметр 'warmup_top' має невірний формат (Кількість популярних прайсів для прогріву, 0 - вимкнено)"),
+            Error::WarmupPauseFormat => (159, Severity::Fatal, 500, "В файлі конфігурації параметр 'warmup_pause' має невірний формат (Пауза між генераціями прогріву в мс)"),
+            Error::ColumnsFormat => (160, Severity::Fatal, 500, "Файл колонок прайса columns.json має невірний формат"),
+
+            Error::InitB2bConnect => (180, Severity::Fatal, 503, "Неможливо встановити з'єднання з базою даних B2B"),
+            Error::InitB2bQuery => (181, Severity::Fatal, 503, "Помилка запиту з базою даних B2B"),
+            Error::InitLogConnect => (182, Severity::Fatal, 503, "Неможливо встановити з'єднання з базою даних логістики товарів"),
+            Error::InitLogQuery => (183, Severity::Fatal, 503, "Помилка запиту з базою даних логістики товарів"),
+
+            Error::ServerStart => (200, Severity::Fatal, 500, "Помилка запуска сервера"),
+            Error::IrcConnect => (201, Severity::Fatal, 500, "Помилка при з’єднання до IRC сервера"),
+            Error::IrcTimeout => (202, Severity::Fatal, 500, "Не вдалося приднатися до IRC сервера"),
+            Error::IrcSend => (203, Severity::Fatal, 500, "Помилка відправлення сигналу до IRC сервера"),
+            Error::IrcReadTimeout => (204, Severity::Fatal, 500, "Не вдалося встановити timeout читання даних від IRC сервера"),
+            Error::IrcRead => (205, Severity::Fatal, 500, "Не вдалося прочитати дані від IRC сервера"),
+            Error::IrcEmpty => (206, Severity::Fatal, 500, "Дані відсутні при читанні від IRC сервера"),
+            Error::IrcData => (207, Severity::Fatal, 500, "Невірні дані отримані при читанні від IRC сервера"),
+            Error::IrcPid => (208, Severity::Fatal, 500, "IRC сервер повернув невірний PID"),
+
+            Error::IrcPermission => (300, Severity::Fatal, 500, "Відсутні права для запуска IRC сервера"),
+            Error::IrcInUse => (301, Severity::Fatal, 500, "Сокет IRC занятий"),
+            Error::IrcNotAvailable => (302, Severity::Fatal, 500, "Сокет IRC недоступний для користування"),
+            Error::IrcBind => (303, Severity::Fatal, 500, "Неможливо відкрити сокет IRC"),
+            Error::IrcNonBlocking => (304, Severity::Fatal, 500, "Неможливо встановити неблокуючий режим для сокет IRC"),
+
+            Error::TcpPermission => (400, Severity::Fatal, 500, "Відсутні права для запуска TCP сервера"),
+            Error::TcpInUse => (401, Severity::Fatal, 500, "Сокет TCP занятий"),
+            Error::TcpNotAvailable => (402, Severity::Fatal, 500, "Сокет TCP недоступний для користування"),
+            Error::TcpBind => (403, Severity::Fatal, 500, "Неможливо відкрити сокет TCP"),
+            Error::TcpNonBlocking => (404, Severity::Fatal, 500, "Неможливо встановити неблокуючий режим для сокет TCP"),
+
+            Error::QueueTake => (500, Severity::Fatal, 500, "Проблеми з чергою потоків"),
+            Error::WorkerNotFound => (501, Severity::Fatal, 500, "Переплутані з'єднання"),
+            Error::CacheNotDir => (502, Severity::Fatal, 500, "Неможливо використовувати каталог для кеша прайсів"),
+            Error::CacheCreate => (503, Severity::Fatal, 500, "Неможливо створити каталог для кеша прайсів"),
+            Error::WorkerChannel => (504, Severity::Fatal, 500, "Канал повідомлень робочого потоку закрито"),
+
+            Error::DbUrl => (600, Severity::Error, 503, "Некорректна строка підключення до бази даних"),
+            Error::DbConnect => (601, Severity::Error, 503, "Помилка з'єднання з базою даних"),
+            Error::DbQuery => (602, Severity::Error, 503, "Помилка виконання запиту до бази даних"),
+            Error::DbSetup => (603, Severity::Error, 503, "Не вдалося встановити початкові параметри підключення"),
+
+            Error::AuditFile => (700, Severity::Warn, 500, "Не вдалося записати журнал завантажень до файлу access.log"),
+            Error::AuditOverflow => (701, Severity::Error, 500, "Журнал завантажень переповнено, частину записів втрачено"),
+            Error::AuditLost => (702, Severity::Error, 500, "Під час зупинки сервера не вдалося записати журнал завантажень до бази даних, записи втрачено"),
+        }
+    }
+
+    // Каталог помилок у текстовому вигляді
+    pub fn catalogue_text() -> String {
+        let mut text = String::with_capacity(16384);
+        text.push_str(&format!("{:>4}  {:<6}  {:>3}  {}\n", "Код", "Тип", "HTTP", "Опис"));
+        for err in Error::ALL.iter() {
+            text.push_str(&format!("{:>4}  {:<6}  {:>3}  {}\n", err.code(), err.severity().as_str(), err.status(), err.message()));
+        }
+        text
+    }
+
+    // Каталог помилок у форматі JSON
+    pub fn catalogue_json() -> String {
+        let mut data = String::with_capacity(32768);
+        data.push('[');
+        for err in Error::ALL.iter() {
+            data.push_str(&format!("{{\"code\":{},\"name\":\"{:?}\",\"severity\":\"{}\",\"status\":{},\"message\":{}}},", err.code(), err, err.severity().as_str(), err.status(), Value::from(err.message())));
+        }
+        data.pop();
+        data.push(']');
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_unique() {
+        for (i, err) in Error::ALL.iter().enumerate() {
+            // Пошук за кодом повертає ту саму помилку
+            let found = Error::ALL.iter().find(|e| e.code() == err.code()).unwrap();
+            assert_eq!(found, err, "код {} повторюється", err.code());
+            assert_eq!(Error::ALL.iter().position(|e| e == err), Some(i));
+        }
+    }
+}
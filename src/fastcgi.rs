@@ -3,6 +3,8 @@ use std::{net::TcpStream, time::Duration, thread, io::{ErrorKind, Read, Write, E
 use byteorder::{BigEndian, ByteOrder};
 use cast::{u8, u16, u32, usize};
 
+use crate::response::Response;
+
 // FastCGI header
 #[derive(Debug)]
 pub struct Header
@@ -16,7 +18,7 @@ pub struct Header
 }
 
 // FastCGI header type
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum HeaderType {
     BeginRequest,
     AbortRequest,
@@ -471,13 +473,58 @@ impl FastCGI {
     }
 
     // Answer to the WEB server
-    pub fn write_response(header: &Header, answer: Vec<u8>, stream: &mut TcpStream) -> Result<(), Error> {
+    pub fn write_response(header: &Header, response: Response, stream: &mut TcpStream) -> Result<(), Error> {
         let mut data: Vec<u8> = Vec::new();
+        let stderr = response.stderr_data();
+        if stderr.len() > 0 {
+            FastCGI::stream_records(header, HeaderType::Stderr, stderr, &mut data);
+            FastCGI::stream_records(header, HeaderType::Stderr, Vec::new(), &mut data);
+        }
+        FastCGI::stream_records(header, HeaderType::Stdout, response.stdout(), &mut data);
+        FastCGI::stream_records(header, HeaderType::Stdout, Vec::new(), &mut data);
+        let record = Record {
+            header: Header {
+                version: FASTCGI_VERSION,
+                header_type: HeaderType::EndRequest,
+                request_id: header.request_id,
+                content_length: 8,
+                padding_length: 0,
+                reserved: 0,
+            },
+            data: ContentData::End(End{
+                code: 0,
+                protocol: FASTCGI_REQUEST_COMPLETE,
+                reserved: [0; 3],
+            }),
+        };
+        data.extend_from_slice(&FastCGI::record_array(record)[..]);
+        if let Err(e) = stream.write_all(&data[..]) {
+            return Err(e); 
+        }
+        Ok(())
+    }
+
+    // Split stream data to records, empty data closes the stream
+    fn stream_records(header: &Header, header_type: HeaderType, answer: Vec<u8>, data: &mut Vec<u8>) {
         let len = answer.len();
+        if len == 0 {
+            let record = Record {
+                header: Header {
+                    version: FASTCGI_VERSION,
+                    header_type,
+                    request_id: header.request_id,
+                    content_length: 0,
+                    padding_length: 0,
+                    reserved: 0,
+                },
+                data: ContentData::None,
+            };
+            data.extend_from_slice(&FastCGI::record_array(record)[..]);
+            return;
+        }
         let mut size: u16;
         let mut seek: usize = 0;
         let pack = Rc::new(answer);
-        // Split data to parts
         while seek < len {
             if seek + FASTCGI_MAX_CONTENT_LEN < len {
                 size = u16(FASTCGI_MAX_CONTENT_LEN).unwrap();
@@ -487,7 +534,7 @@ impl FastCGI {
             let record = Record {
                 header: Header {
                     version: FASTCGI_VERSION,
-                    header_type: HeaderType::Stdout,
+                    header_type: header_type.clone(),
                     request_id: header.request_id,
                     content_length: size,
                     padding_length: 0,
@@ -498,38 +545,6 @@ impl FastCGI {
             data.extend_from_slice(&FastCGI::record_array(record)[..]);
             seek += usize(size);
         }
-        let record = Record {
-            header: Header {
-                version: FASTCGI_VERSION,
-                header_type: HeaderType::Stdout,
-                request_id: header.request_id,
-                content_length: 0,
-                padding_length: 0,
-                reserved: 0,
-            },
-            data: ContentData::None,
-        };
-        data.extend_from_slice(&FastCGI::record_array(record)[..]);
-        let record = Record {
-            header: Header {
-                version: FASTCGI_VERSION,
-                header_type: HeaderType::EndRequest,
-                request_id: header.request_id,
-                content_length: 8,
-                padding_length: 0,
-                reserved: 0,
-            },
-            data: ContentData::End(End{
-                code: 0,
-                protocol: FASTCGI_REQUEST_COMPLETE,
-                reserved: [0; 3],
-            }),
-        };
-        data.extend_from_slice(&FastCGI::record_array(record)[..]);
-        if let Err(e) = stream.write_all(&data[..]) {
-            return Err(e); 
-        }
-        Ok(())
     }
 
     // Write abore request
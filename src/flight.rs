@@ -0,0 +1,63 @@
+use std::{collections::HashMap, sync::{Arc, Mutex, Condvar}, time::Duration};
+
+pub const FLIGHT_WAIT: std::time::Duration = Duration::from_secs(120);
+
+// Генерація, що виконується одним з робочих потоків
+#[derive(Debug)]
+struct Slot {
+    done: Mutex<bool>,
+    cond: Condvar,
+}
+
+// Об'єднання однакових одночасних генерацій прайса.
+// Перший потік отримує FlightGuard і генерує файл, інші чекають його завершення
+#[derive(Debug)]
+pub struct Flight {
+    list: Mutex<HashMap<String, Arc<Slot>>>,
+}
+
+// Поки існує - генерація за ключем належить поточному потоку
+pub struct FlightGuard {
+    flight: Arc<Flight>,
+    key: String,
+    slot: Arc<Slot>,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        {
+            let mut list = Mutex::lock(&self.flight.list).unwrap();
+            list.remove(&self.key);
+        }
+        let mut done = Mutex::lock(&self.slot.done).unwrap();
+        *done = true;
+        self.slot.cond.notify_all();
+    }
+}
+
+impl Flight {
+    pub fn new() -> Arc<Flight> {
+        Arc::new(Flight {
+            list: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Some - генерувати поточному потоку, None - інший потік завершив генерацію (або час очікування вичерпано)
+    pub fn start(flight: Arc<Flight>, key: &str) -> Option<FlightGuard> {
+        let slot;
+        {
+            let mut list = Mutex::lock(&flight.list).unwrap();
+            match list.get(key) {
+                Some(s) => slot = Arc::clone(s),
+                None => {
+                    let slot = Arc::new(Slot { done: Mutex::new(false), cond: Condvar::new() });
+                    list.insert(key.to_owned(), Arc::clone(&slot));
+                    return Some(FlightGuard { flight: Arc::clone(&flight), key: key.to_owned(), slot });
+                },
+            }
+        }
+        let done = Mutex::lock(&slot.done).unwrap();
+        if let Err(_) = slot.cond.wait_timeout_while(done, FLIGHT_WAIT, |done| !*done) { }
+        None
+    }
+}
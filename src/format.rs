@@ -0,0 +1,170 @@
+use std::{fs::{File, rename, read, remove_file}, io::Write, sync::{Arc, RwLock}};
+
+use crate::{price::{PriceItem, ItemShow}, data::Category, init::Init, meta::PriceMeta, format_xml::FormatXml};
+
+// Магазин у заголовку фідів для маркетплейсів
+pub const SHOP_NAME: &str = "Brain";
+pub const SHOP_URL: &str = "https://brain.com.ua";
+
+// Елемент XML з екранованим значенням, порожнє значення не виводиться
+pub fn element(data: &mut String, tag: &str, val: &str) {
+    if val.len() > 0 {
+        data.push_str(&format!("<{}>{}</{}>", tag, FormatXml::escape_xml(val), tag));
+    }
+}
+
+// Файл прайса записується в {file}.tmp і з'являється під своїм ім'ям лише після успішного завершення.
+// Незавершений .tmp файл видаляється. Без файлу весь результат залишається в пам'яті
+pub struct Output {
+    file: Option<File>,
+    data: Vec<u8>,
+    flush: usize,                       // Розмір буфера, після якого він записується у файл
+    written: bool,                      // Частину вмісту вже записано у файл
+    tmp: String,
+    name: String,
+    done: bool,
+}
+
+impl Output {
+    pub fn create(name: &str, capacity: usize, flush: usize) -> Option<Output> {
+        let tmp = format!("{}.tmp", name);
+        let file = match File::create(&tmp) {
+            Ok(file) => file,
+            Err(_) => return None,
+        };
+        Some(Output {
+            file: Some(file),
+            data: Vec::with_capacity(capacity),
+            flush,
+            written: false,
+            tmp,
+            name: name.to_owned(),
+            done: false,
+        })
+    }
+
+    pub fn memory(capacity: usize) -> Output {
+        Output {
+            file: None,
+            data: Vec::with_capacity(capacity),
+            flush: usize::MAX,
+            written: false,
+            tmp: String::new(),
+            name: String::new(),
+            done: true,
+        }
+    }
+
+    pub fn push(&mut self, text: &str) -> Option<()> {
+        self.write(text.as_bytes())
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Option<()> {
+        self.data.extend_from_slice(data);
+        if self.data.len() > self.flush {
+            return self.flush();
+        }
+        Some(())
+    }
+
+    fn flush(&mut self) -> Option<()> {
+        let file = self.file.as_mut()?;
+        if let Err(_) = file.write_all(&self.data) {
+            return None;
+        }
+        self.data.clear();
+        self.written = true;
+        Some(())
+    }
+
+    // Дописати буфер, записати метадані та перейменувати файл, повернути його вміст.
+    // Метадані з'являються раніше за файл, тож файл під своїм ім'ям завжди має метадані.
+    // Прайс, що вмістився в буфер, повертається з пам'яті. Більший прайс пишеться у файл частинами,
+    // щоб буфер не ріс до розміру всього прайса поряд з даними форматування, і наприкінці читається з диску
+    pub fn commit(mut self, meta: &mut PriceMeta) -> Option<Vec<u8>> {
+        let res = if self.written {
+            self.flush()?;
+            match read(&self.tmp) {
+                Ok(res) => res,
+                Err(_) => return None,
+            }
+        } else {
+            let file = self.file.as_mut()?;
+            if let Err(_) = file.write_all(&self.data) {
+                return None;
+            }
+            std::mem::take(&mut self.data)
+        };
+        meta.etag = PriceMeta::etag(&res);
+        if !meta.write(&self.name) {
+            return None;
+        }
+        if let Err(_) = rename(&self.tmp, &self.name) {
+            if let Err(_) = remove_file(PriceMeta::name(&self.name)) { }
+            return None;
+        }
+        self.done = true;
+        Some(res)
+    }
+
+    // Результат, зібраний в пам'яті
+    pub fn take(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.data)
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if !self.done {
+            if let Err(_) = remove_file(&self.tmp) { }
+        }
+    }
+}
+
+// Формат файлу прайса.
+// Порядок викликів: begin, category для кожної категорії в порядку дерева, header, row для кожного товару, finish
+pub trait PriceFormatter<'a> {
+    fn begin(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], rows: usize) -> Option<()>;
+
+    // Категорія дерева, depth 0 - верхній рівень
+    fn category(&mut self, _out: &mut Output, _category: &Category) -> Option<()> {
+        Some(())
+    }
+
+    fn header(&mut self, _out: &mut Output, _columns: &[&ItemShow<'a>]) -> Option<()> {
+        Some(())
+    }
+
+    fn row(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()>;
+
+    fn finish(&mut self, out: &mut Output) -> Option<()>;
+
+    fn write(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], items: &[&'a PriceItem], categories: &[Category]) -> Option<()> {
+        self.begin(out, columns, items.len())?;
+        for category in categories {
+            self.category(out, category)?;
+        }
+        self.header(out, columns)?;
+        for &item in items {
+            self.row(out, columns, item)?;
+        }
+        self.finish(out)
+    }
+
+    fn make(&mut self, filename: &str, meta: &mut PriceMeta, columns: &[&ItemShow<'a>], items: &[&'a PriceItem], categories: &[Category], init: Arc<RwLock<Init>>) -> Option<Vec<u8>> {
+        let mut out;
+        {
+            let i = RwLock::read(&init).unwrap();
+            out = Output::create(filename, i.file_buffer_capacity, i.file_flush_buffer_capacity)?;
+        }
+        self.write(&mut out, columns, items, categories)?;
+        out.commit(meta)
+    }
+
+    // Прайс в пам'яті без файлу кеша
+    fn render(&mut self, columns: &[&ItemShow<'a>], items: &[&'a PriceItem], categories: &[Category]) -> Option<Vec<u8>> {
+        let mut out = Output::memory(items.len() * 1024 + 4096);
+        self.write(&mut out, columns, items, categories)?;
+        Some(out.take())
+    }
+}
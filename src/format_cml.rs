@@ -0,0 +1,172 @@
+use std::{collections::HashSet, io::{Cursor, Write}};
+
+use chrono::Local;
+use zip::{ZipWriter, write::FileOptions, CompressionMethod};
+
+use crate::{price::{PriceItem, ItemShow, ValueType}, format::{PriceFormatter, Output, SHOP_NAME, element}, format_xml::FormatXml, data::Category};
+
+// Типи цін: (поле товару, Ид, українською, російською, валюта).
+// Тип ціни додається, якщо колонка є в прайсі і хоча б один товар має ненульову ціну
+const PRICES: [(&str, &str, &str, &str, &str); 4] = [
+    ("price_usd", "purchase_usd", "Закупівельна", "Закупочная", "USD"),
+    ("price_uah", "purchase_uah", "Закупівельна, грн", "Закупочная, грн", "UAH"),
+    ("recommended_price", "recommended", "Рекомендована", "Рекомендованная", "UAH"),
+    ("retail_price", "retail", "Роздрібна", "Розничная", "UAH"),
+];
+const CATALOG_ID: &str = "brain";
+
+// CommerceML 2 для обміну з 1С та BAS: zip з import.xml (класифікатор та каталог) і offers.xml (ціни та залишки)
+pub struct FormatCML {
+    ua: bool,
+    date: String,
+    barcode: bool,                      // Штрихкод товару доступний компанії
+    prices: Vec<(usize, usize, bool)>,  // Індекс колонки, індекс в PRICES, тип ціни має ненульові ціни
+    open: Vec<usize>,                   // Глибина відкритих груп класифікатора
+    groups: HashSet<u32>,               // Групи класифікатора, товари інших категорій не вивантажуються
+    import: String,
+    offers: String,                     // Пропозиції, заголовок з типами цін додається в кінці
+}
+
+impl FormatCML {
+    pub fn new(ua: bool) -> FormatCML {
+        FormatCML {
+            ua,
+            date: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+            barcode: false,
+            prices: Vec::with_capacity(PRICES.len()),
+            open: Vec::with_capacity(8),
+            groups: HashSet::with_capacity(1024),
+            import: String::with_capacity(1048576),
+            offers: String::with_capacity(1048576),
+        }
+    }
+
+    // Закрити групи з глибиною не меншою за depth, повертає true, якщо групу було закрито
+    fn close(&mut self, depth: usize) -> bool {
+        let mut closed = false;
+        while let Some(&top) = self.open.last() {
+            if top < depth {
+                break;
+            }
+            self.open.pop();
+            self.import.push_str("</Группа>");
+            if top > depth {
+                self.import.push_str("</Группы>");
+            }
+            closed = true;
+        }
+        closed
+    }
+}
+
+impl<'a> PriceFormatter<'a> for FormatCML {
+    fn begin(&mut self, _out: &mut Output, columns: &[&ItemShow<'a>], _rows: usize) -> Option<()> {
+        self.barcode = columns.iter().any(|col| col.source == "ean");
+        self.import.push_str(&format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<КоммерческаяИнформация ВерсияСхемы=\"2.05\" ДатаФормирования=\"{}\"><Классификатор><Ид>{}</Ид><Наименование>{}</Наименование><Группы>",
+            self.date, CATALOG_ID, SHOP_NAME));
+        for (p, (source, ..)) in PRICES.iter().enumerate() {
+            if let Some(i) = columns.iter().position(|col| col.source == *source) {
+                self.prices.push((i, p, false));
+            }
+        }
+        Some(())
+    }
+
+    // Група відкривається без закриваючого тегу, підгрупи вкладаються в її Группы
+    fn category(&mut self, _out: &mut Output, category: &Category) -> Option<()> {
+        let closed = self.close(category.depth);
+        if !closed && self.open.len() > 0 {
+            self.import.push_str("<Группы>");
+        }
+        self.import.push_str(&format!("<Группа><Ид>{}</Ид><Наименование>{}</Наименование>", category.id, FormatXml::escape_xml(if self.ua { &category.ua } else { &category.ru })));
+        self.open.push(category.depth);
+        self.groups.insert(category.id);
+        Some(())
+    }
+
+    fn header(&mut self, _out: &mut Output, _columns: &[&ItemShow<'a>]) -> Option<()> {
+        self.close(0);
+        self.import.push_str(&format!("</Группы></Классификатор><Каталог СодержитТолькоИзменения=\"false\"><Ид>{}</Ид><ИдКлассификатора>{}</ИдКлассификатора><Наименование>{}</Наименование><Товары>",
+            CATALOG_ID, CATALOG_ID, SHOP_NAME));
+        Some(())
+    }
+
+    fn row(&mut self, _out: &mut Output, columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()> {
+        // Товар з вимкненої категорії або поза деревом посилався б на відсутню групу
+        if !self.groups.contains(&item.category_id) {
+            return Some(());
+        }
+        let data = &mut self.import;
+        data.push_str(&format!("<Товар><Ид>{}</Ид>", item.id));
+        element(data, "Артикул", &item.articul);
+        element(data, "Наименование", &item.name);
+        data.push_str("<БазоваяЕдиница Код=\"796\" НаименованиеПолноеЕдиницы=\"Штука\">шт</БазоваяЕдиница>");
+        data.push_str(&format!("<Группы><Ид>{}</Ид></Группы>", item.category_id));
+        element(data, "Описание", &item.description);
+        if self.barcode {
+            element(data, "Штрихкод", &item.ean);
+        }
+        if item.vendor.len() > 0 {
+            data.push_str(&format!("<Изготовитель><Наименование>{}</Наименование></Изготовитель>", FormatXml::escape_xml(&item.vendor)));
+        }
+        data.push_str("<ЗначенияРеквизитов>");
+        data.push_str(&format!("<ЗначениеРеквизита><Наименование>Код</Наименование><Значение>{}</Значение></ЗначениеРеквизита>", FormatXml::escape_xml(&item.code)));
+        if item.model.len() > 0 {
+            data.push_str(&format!("<ЗначениеРеквизита><Наименование>Модель</Наименование><Значение>{}</Значение></ЗначениеРеквизита>", FormatXml::escape_xml(&item.model)));
+        }
+        data.push_str("</ЗначенияРеквизитов></Товар>");
+
+        let data = &mut self.offers;
+        data.push_str(&format!("<Предложение><Ид>{}</Ид>", item.id));
+        element(data, "Артикул", &item.articul);
+        element(data, "Наименование", &item.name);
+        data.push_str("<БазоваяЕдиница Код=\"796\" НаименованиеПолноеЕдиницы=\"Штука\">шт</БазоваяЕдиница><Цены>");
+        for (i, p, used) in self.prices.iter_mut() {
+            // Ціна без права компанії вибирається з бази як 0
+            if let ValueType::Money(price) = (columns[*i].get)(item) {
+                if price <= 0.0 {
+                    continue;
+                }
+                *used = true;
+                let (_, id, _, _, currency) = PRICES[*p];
+                data.push_str(&format!("<Цена><Представление>{:.2} {} за шт</Представление><ИдТипаЦены>{}</ИдТипаЦены><ЦенаЗаЕдиницу>{:.2}</ЦенаЗаЕдиницу><Валюта>{}</Валюта><Единица>шт</Единица><Коэффициент>1</Коэффициент></Цена>",
+                    price, currency, id, price, currency));
+            }
+        }
+        // Количество в 1С числове: товар не на складі або з кількістю-діапазоном вивантажується з нулем
+        let count = if item.stock == "1" { item.available.parse::<u32>().unwrap_or(0) } else { 0 };
+        data.push_str(&format!("</Цены><Количество>{}</Количество></Предложение>", count));
+        Some(())
+    }
+
+    fn finish(&mut self, out: &mut Output) -> Option<()> {
+        self.import.push_str("</Товары></Каталог></КоммерческаяИнформация>");
+        let mut offers = String::with_capacity(self.offers.len() + 4096);
+        offers.push_str(&format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<КоммерческаяИнформация ВерсияСхемы=\"2.05\" ДатаФормирования=\"{}\"><ПакетПредложений СодержитТолькоИзменения=\"false\"><Ид>{}#</Ид><Наименование>{}</Наименование><ИдКаталога>{}</ИдКаталога><ИдКлассификатора>{}</ИдКлассификатора><Владелец><Ид>{}</Ид><Наименование>{}</Наименование></Владелец><ТипыЦен>",
+            self.date, CATALOG_ID, SHOP_NAME, CATALOG_ID, CATALOG_ID, CATALOG_ID, SHOP_NAME));
+        for &(_, p, used) in &self.prices {
+            if used {
+                let (_, id, ua, ru, currency) = PRICES[p];
+                offers.push_str(&format!("<ТипЦены><Ид>{}</Ид><Наименование>{}</Наименование><Валюта>{}</Валюта></ТипЦены>", id, if self.ua { ua } else { ru }, currency));
+            }
+        }
+        offers.push_str("</ТипыЦен><Предложения>");
+        offers.push_str(&self.offers);
+        offers.push_str("</Предложения></ПакетПредложений></КоммерческаяИнформация>");
+        self.offers = offers;
+        let options = FileOptions::default().compression_level(Some(3)).compression_method(CompressionMethod::Deflated);
+        let mut zip = ZipWriter::new(Cursor::new(Vec::with_capacity(self.import.len() / 4 + self.offers.len() / 4)));
+        for (name, data) in [("import.xml", &self.import), ("offers.xml", &self.offers)] {
+            if let Err(_) = zip.start_file(name, options) {
+                return None;
+            }
+            if let Err(_) = zip.write_all(data.as_bytes()) {
+                return None;
+            }
+        }
+        match zip.finish() {
+            Ok(res) => out.write(&res.into_inner()),
+            Err(_) => None,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{price::{PriceItem, ItemShow, ValueType}, format::{PriceFormatter, Output}, param::CsvOptions};
+
+// CSV та TSV (RFC 4180), рядок заголовка мовою прайса з каталогу колонок
+pub struct FormatCSV {
+    options: CsvOptions,
+    ua: bool,
+}
+
+impl FormatCSV {
+    pub fn new(options: CsvOptions, ua: bool) -> FormatCSV {
+        FormatCSV {
+            options,
+            ua,
+        }
+    }
+
+    fn field(&self, data: &mut String, val: &str) {
+        let tsv = self.options.delimiter == '\t';
+        if tsv {
+            // В TSV немає екранування, роздільники всередині значення замінюються пробілом
+            for c in val.chars() {
+                match c {
+                    '\t' | '\r' | '\n' => data.push(' '),
+                    c => data.push(c),
+                }
+            }
+            return;
+        }
+        let quote = self.options.quote_all || val.contains(|c| c == self.options.delimiter || c == '"' || c == '\r' || c == '\n');
+        if quote {
+            data.push('"');
+            data.push_str(&val.replace("\"", "\"\""));
+            data.push('"');
+        } else {
+            data.push_str(val);
+        }
+    }
+
+    fn money(&self, val: f32, decimals: usize) -> String {
+        let val = format!("{:.*}", decimals, val);
+        if self.options.decimal_comma {
+            val.replace(".", ",")
+        } else {
+            val
+        }
+    }
+}
+
+impl<'a> PriceFormatter<'a> for FormatCSV {
+    fn begin(&mut self, out: &mut Output, _columns: &[&ItemShow<'a>], _rows: usize) -> Option<()> {
+        if self.options.bom {
+            return out.write(&[0xEF, 0xBB, 0xBF]);
+        }
+        Some(())
+    }
+
+    fn header(&mut self, out: &mut Output, columns: &[&ItemShow<'a>]) -> Option<()> {
+        let mut data = String::with_capacity(512);
+        for (i, col) in columns.iter().enumerate() {
+            if i > 0 {
+                data.push(self.options.delimiter);
+            }
+            self.field(&mut data, col.title(self.ua));
+        }
+        data.push_str("\r\n");
+        out.push(&data)
+    }
+
+    fn row(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()> {
+        let mut data = String::with_capacity(1024);
+        for (i, col) in columns.iter().enumerate() {
+            if i > 0 {
+                data.push(self.options.delimiter);
+            }
+            match (col.get)(item) {
+                ValueType::String(v) => self.field(&mut data, v),
+                ValueType::Money(v) => data.push_str(&self.money(v, col.decimals)),
+                ValueType::Index(v) => data.push_str(&v.to_string()),
+            }
+        }
+        data.push_str("\r\n");
+        out.push(&data)
+    }
+
+    fn finish(&mut self, _out: &mut Output) -> Option<()> {
+        Some(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use crate::{price::{PriceItem, ItemShow}, format::{PriceFormatter, Output, SHOP_NAME, SHOP_URL, element}, data::Category};
+
+// Фід Google Merchant Center (RSS 2.0 з простором імен g:).
+// Google вимагає ціну для кожного товару, тож товари з нульовою ціною в гривнях пропускаються
+pub struct FormatGMC {
+    ua: bool,
+    gtin: bool,                         // Колонки прайса містять штрихкод для g:gtin
+    path: Vec<String>,                  // Назви категорій від верхнього рівня до поточної
+    product_type: HashMap<u32, String>, // Шлях категорії "Категорія > Підкатегорія"
+}
+
+impl FormatGMC {
+    pub fn new(ua: bool) -> FormatGMC {
+        FormatGMC {
+            ua,
+            gtin: false,
+            path: Vec::with_capacity(8),
+            product_type: HashMap::with_capacity(1024),
+        }
+    }
+
+    // Товар на складі - in_stock, під замовлення з терміном поставки - backorder
+    fn availability(item: &PriceItem) -> &'static str {
+        if item.stock == "1" {
+            "in_stock"
+        } else if item.day_delivery != "0" {
+            "backorder"
+        } else {
+            "out_of_stock"
+        }
+    }
+}
+
+impl<'a> PriceFormatter<'a> for FormatGMC {
+    fn begin(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], _rows: usize) -> Option<()> {
+        self.gtin = columns.iter().any(|col| col.source == "ean");
+        out.push(&format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\" xmlns:g=\"http://base.google.com/ns/1.0\"><channel><title>{}</title><link>{}</link><description>{}</description>",
+            SHOP_NAME, SHOP_URL, SHOP_NAME))
+    }
+
+    fn category(&mut self, _out: &mut Output, category: &Category) -> Option<()> {
+        self.path.truncate(category.depth);
+        self.path.push(if self.ua { category.ua.clone() } else { category.ru.clone() });
+        self.product_type.insert(category.id, self.path.join(" > "));
+        Some(())
+    }
+
+    fn row(&mut self, out: &mut Output, _columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()> {
+        if item.price_uah <= 0.0 {
+            return Some(());
+        }
+        let mut data = String::with_capacity(2048);
+        data.push_str(&format!("<item><g:id>{}</g:id>", item.id));
+        element(&mut data, "g:title", &item.name);
+        element(&mut data, "g:description", &item.description);
+        element(&mut data, "g:link", &item.url);
+        data.push_str(&format!("<g:price>{:.2} UAH</g:price><g:availability>{}</g:availability><g:condition>new</g:condition>", item.price_uah, FormatGMC::availability(item)));
+        element(&mut data, "g:brand", &item.vendor);
+        element(&mut data, "g:mpn", &item.articul);
+        if self.gtin {
+            element(&mut data, "g:gtin", &item.ean);
+        }
+        if let Some(product_type) = self.product_type.get(&item.category_id) {
+            element(&mut data, "g:product_type", product_type);
+        }
+        data.push_str("</item>");
+        out.push(&data)
+    }
+
+    fn finish(&mut self, out: &mut Output) -> Option<()> {
+        out.push("</channel></rss>")
+    }
+}
@@ -1,95 +1,69 @@
-use std::{collections::HashMap, fs::{File, rename, read}, io::Write, sync::{RwLock, Arc}};
+use crate::{price::{PriceItem, ItemShow, ValueType}, format::{PriceFormatter, Output}};
 
-use crate::{price::{PriceItem, Show, ValueType}, param::PriceVolume, init::Init};
-
-pub struct FormatJSON { }
+pub struct FormatJSON {
+    first: bool,
+}
 
 impl FormatJSON {
+    pub fn new() -> FormatJSON {
+        FormatJSON {
+            first: true,
+        }
+    }
 
-    fn escape_json(val: &str) -> String {
-        let mut new = val.replace("\\", "\\\\");
-        new = new.replace("\"", "\\\"");
-        new = new.replace("/", "\\/");
-        new = new.replace("\t", "\\t");
-        new = new.replace("\n", "\\n");
-        new = new.replace("\r", "\\r");
+    pub fn escape_json(val: &str) -> String {
+        let mut new = String::with_capacity(val.len() + 8);
+        for c in val.chars() {
+            match c {
+                '\\' => new.push_str("\\\\"),
+                '"' => new.push_str("\\\""),
+                '/' => new.push_str("\\/"),
+                '\t' => new.push_str("\\t"),
+                '\n' => new.push_str("\\n"),
+                '\r' => new.push_str("\\r"),
+                c if (c as u32) < 0x20 => new.push_str(&format!("\\u{:04x}", c as u32)),
+                c => new.push(c),
+            }
+        }
         new
     }
 
-    pub fn make(items: &HashMap<u32, PriceItem>, filename: &str, volume: &PriceVolume, rozn: bool, r3: bool, ean: bool, init: Arc<RwLock<Init>>) -> Option<Vec<u8>> {
-        let init_read = RwLock::read(&init).unwrap();
-
-        let mut show = Show::new();
-        match volume {
-            PriceVolume::Local => {
-                for item in &mut show.list {
-                    if item.local || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-            PriceVolume::Full => {
-                for item in &mut show.list {
-                    if item.full || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-            PriceVolume::Short => {
-                for item in &mut show.list {
-                    if item.short || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-            PriceVolume::FullUAH => {
-                for item in &mut show.list {
-                    if item.full_uah || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-        }
-        let tmp = format!("{}.tmp", filename);
-        let path = std::path::Path::new(&tmp);
-        let mut file = match File::create(path) {
-            Ok(file) => file,
-            Err(_) => return None,
-        };
-        let mut data = String::with_capacity(init_read.file_buffer_capacity);
-        data.push_str("{");
-        for (_, price) in items {
-            data.push_str(&format!("\"{}\":{{", price.id));
-            for item in &show.list {
-                if let Some(_) = &item.index {
-                    match (item.get)(price) {
-                        ValueType::String(v) => data.push_str(&format!("\"{}\":\"{}\",", FormatJSON::escape_json(&item.name), FormatJSON::escape_json(v))),
-                        ValueType::Money(v) => data.push_str(&format!("\"{}\":{:.2},", FormatJSON::escape_json(&item.name), v)),
-                        ValueType::Index(v) => data.push_str(&format!("\"{}\":{},", FormatJSON::escape_json(&item.name), v)),
-                    }
-                }
+    // Товар як JSON об'єкт з вибраних колонок
+    pub fn object<'a>(columns: &[&ItemShow<'a>], item: &'a PriceItem) -> String {
+        let mut data = String::with_capacity(1024);
+        data.push('{');
+        for (i, col) in columns.iter().enumerate() {
+            if i > 0 {
+                data.push(',');
             }
-            data.pop();
-            if data.len() > init_read.file_flush_buffer_capacity {
-                if let Err(_) = file.write_all(data.as_bytes()) {
-                    return None;
-                }
-                data.clear();
+            match (col.get)(item) {
+                ValueType::String(v) => data.push_str(&format!("\"{}\":\"{}\"", FormatJSON::escape_json(col.name), FormatJSON::escape_json(v))),
+                ValueType::Money(v) => data.push_str(&format!("\"{}\":{:.*}", FormatJSON::escape_json(col.name), col.decimals, v)),
+                ValueType::Index(v) => data.push_str(&format!("\"{}\":{}", FormatJSON::escape_json(col.name), v)),
             }
-            data.push_str("},");
-        }
-        data.pop();
-        data.push_str("}");
-        if let Err(_) = file.write_all(data.as_bytes()) {
-            return None;
         }
-        if let Err(_) = rename(&tmp, filename) {
-            return None;
-        }
-        let path = std::path::Path::new(filename);
-        match read(path) {
-            Ok(str) => Some(str),
-            Err(_) => None,
+        data.push('}');
+        data
+    }
+}
+
+impl<'a> PriceFormatter<'a> for FormatJSON {
+    fn begin(&mut self, out: &mut Output, _columns: &[&ItemShow<'a>], _rows: usize) -> Option<()> {
+        out.push("{")
+    }
+
+    fn row(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()> {
+        let mut data = String::with_capacity(1024);
+        if !self.first {
+            data.push(',');
         }
+        self.first = false;
+        data.push_str(&format!("\"{}\":", item.id));
+        data.push_str(&FormatJSON::object(columns, item));
+        out.push(&data)
+    }
+
+    fn finish(&mut self, out: &mut Output) -> Option<()> {
+        out.push("}")
     }
-}
\ No newline at end of file
+}
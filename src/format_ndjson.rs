@@ -0,0 +1,27 @@
+use crate::{price::{PriceItem, ItemShow}, format::{PriceFormatter, Output}, format_json::FormatJSON};
+
+// Newline-delimited JSON: один товар на рядок, клієнт обробляє прайс потоково.
+// З цього ж файлу вирізаються сторінки JSON API
+pub struct FormatNDJSON {}
+
+impl FormatNDJSON {
+    pub fn new() -> FormatNDJSON {
+        FormatNDJSON {}
+    }
+}
+
+impl<'a> PriceFormatter<'a> for FormatNDJSON {
+    fn begin(&mut self, _out: &mut Output, _columns: &[&ItemShow<'a>], _rows: usize) -> Option<()> {
+        Some(())
+    }
+
+    fn row(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()> {
+        let mut data = FormatJSON::object(columns, item);
+        data.push('\n');
+        out.push(&data)
+    }
+
+    fn finish(&mut self, _out: &mut Output) -> Option<()> {
+        Some(())
+    }
+}
@@ -1,17 +1,32 @@
-use std::{collections::HashMap, sync::{Arc, RwLock}, fs::{File, rename, read}, io::Write};
+use crate::{price::{PriceItem, ItemShow, ValueType}, format::{PriceFormatter, Output}, data::Category};
 
-use crate::{price::{PriceItem, Show, ValueType}, param::{PriceVolume, Lang}, init::Init, db::DB, log::Log};
-
-pub struct Category {
+// Категорія, для якої ще невідомо, чи є в неї підкатегорії
+struct Pending {
     id: u32,
     name: String,
-    parent_id: u32,
+    depth: usize,
 }
 
-pub struct FormatXml { }
+pub struct FormatXml {
+    categories: bool,                   // Додати розділ categories (обсяги local та full)
+    ua: bool,
+    pending: Option<Pending>,
+    open: Vec<usize>,                   // Глибина відкритих тегів категорій
+    started: bool,                      // Тег categories вже відкрито
+}
 
 impl FormatXml {
-    fn escape_xml(val: &str) -> String {
+    pub fn new(categories: bool, ua: bool) -> FormatXml {
+        FormatXml {
+            categories,
+            ua,
+            pending: None,
+            open: Vec::with_capacity(8),
+            started: false,
+        }
+    }
+
+    pub fn escape_xml(val: &str) -> String {
         let mut new = val.replace("&", "&amp;");
         new = new.replace("\"", "&quot;");
         new = new.replace("'", "&apos;");
@@ -20,145 +35,84 @@ impl FormatXml {
         new
     }
 
-    pub fn make(items: &HashMap<u32, PriceItem>, filename: &str, volume: &PriceVolume, rozn: bool, r3: bool, ean: bool, lang: &Lang, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Option<Vec<u8>> {
-        let mut show = Show::new();
-        let init_clone = Arc::clone(&init);
-        let init_read = RwLock::read(&init_clone).unwrap();
-        let cat;
-        match volume {
-            PriceVolume::Local => {
-                cat = match FormatXml::get_categories(lang, init, log) {
-                    Some(cat) => cat,
-                    None => return None,
-                };
-                for item in &mut show.list {
-                    if item.local || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-            PriceVolume::Full => {
-                cat = match FormatXml::get_categories(lang, init, log) {
-                    Some(cat) => cat,
-                    None => return None,
-                };
-                for item in &mut show.list {
-                    if item.full || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-            PriceVolume::Short => {
-                cat = "".to_owned();
-                for item in &mut show.list {
-                    if item.short || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-            PriceVolume::FullUAH => {
-                cat = "".to_owned();
-                for item in &mut show.list {
-                    if item.full_uah || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean) {
-                        item.index = Some("".to_owned());
-                    }
-                }
-            },
-        }
-        let tmp = format!("{}.tmp", filename);
-        let path = std::path::Path::new(&tmp);
-        let mut file = match File::create(path) {
-            Ok(file) => file,
-            Err(_) => return None,
-        };
-        let mut data = String::with_capacity(init_read.file_buffer_capacity);
-        data.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<price>");
-        data.push_str(&cat);
-        data.push_str("<products>");
-        for (_, price) in items {
-            data.push_str("<product");
-            for item in &show.list {
-                if let Some(_) = &item.index {
-                    match (item.get)(price) {
-                        ValueType::String(v) => data.push_str(&format!(" {}=\"{}\"", FormatXml::escape_xml(&item.name), FormatXml::escape_xml(v))),
-                        ValueType::Money(v) => data.push_str(&format!(" {}=\"{:.2}\"", FormatXml::escape_xml(&item.name), v)),
-                        ValueType::Index(v) => data.push_str(&format!(" {}=\"{}\"", FormatXml::escape_xml(&item.name), v)),
-                    }
-                    if data.len() > init_read.file_buffer_capacity {
-                        if let Err(_) = file.write_all(data.as_bytes()) {
-                            return None;
-                        }
-                        data.clear();
-                    }
-                }
+    fn tag(depth: usize) -> &'static str {
+        if depth == 0 { "category" } else { "subcategory" }
+    }
+
+    // Записати попередню категорію: з підкатегоріями - відкритий тег, без них - порожній.
+    // next - глибина наступної категорії, None - дерево завершено
+    fn close(&mut self, next: Option<usize>) -> String {
+        let mut data = String::with_capacity(256);
+        if let Some(p) = self.pending.take() {
+            let children = next.map_or(false, |next| next > p.depth);
+            data.push_str(&format!("<{} id=\"{}\" name=\"{}\"{}>", FormatXml::tag(p.depth), p.id, FormatXml::escape_xml(&p.name), if children { "" } else { "/" }));
+            if children {
+                self.open.push(p.depth);
             }
-            data.push_str("/>");
         }
-        data.push_str("</products>");
-        if let Err(_) = file.write_all(data.as_bytes()) {
-            return None;
+        let next = next.unwrap_or(0);
+        while let Some(&depth) = self.open.last() {
+            if depth < next {
+                break;
+            }
+            self.open.pop();
+            data.push_str(&format!("</{}>", FormatXml::tag(depth)));
         }
-        if let Err(_) = rename(&tmp, filename) {
-            return None;
+        data
+    }
+}
+
+impl<'a> PriceFormatter<'a> for FormatXml {
+    fn begin(&mut self, out: &mut Output, _columns: &[&ItemShow<'a>], _rows: usize) -> Option<()> {
+        out.push("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<price>")
+    }
+
+    fn category(&mut self, out: &mut Output, category: &Category) -> Option<()> {
+        if !self.categories {
+            return Some(());
         }
-        let path = std::path::Path::new(filename);
-        match read(path) {
-            Ok(str) => Some(str),
-            Err(_) => None,
+        let mut data = String::with_capacity(256);
+        if !self.started {
+            self.started = true;
+            data.push_str("<categories>");
         }
+        data.push_str(&self.close(Some(category.depth)));
+        self.pending = Some(Pending {
+            id: category.id,
+            name: if self.ua { category.ua.clone() } else { category.ru.clone() },
+            depth: category.depth,
+        });
+        out.push(&data)
     }
 
-    fn get_categories(lang: &Lang, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Option<String> {
-        let init = RwLock::read(&init).unwrap();
-        let mut db = match DB::new(&init.db_b2b, Arc::clone(&log)) {
-            Some(db) => db,
-            None => return None,
-        };
-        let sql = match lang {
-            Lang::UA => "SELECT categoryid as id, name_ua as name, parent FROM SC_categories where disabled=0 ORDER BY sort_order",
-            Lang::RU => "SELECT categoryid as id, name_ru as name, parent FROM SC_categories where disabled=0 ORDER BY sort_order",
-        };
-        match db.query(&sql) {
-            Some(result) => {
-                let row: Vec<(u32, String, u32)> = result;
-                let mut data = String::with_capacity(init.file_buffer_capacity);
-                let mut cat: Vec<Category> = Vec::with_capacity(init.category_capacity);
-                data.push_str("<categories>");
-                for (category_id, name, parent_id) in row {
-                    cat.push(Category { id: category_id, name, parent_id });
-                }
-                for c in &cat {
-                    if c.parent_id == 1 {
-                        let ct = FormatXml::build_tree_cat(&cat, c.id, init.small_buffer_capacity);
-                        if ct.len() == 0 {
-                            data.push_str(&format!("<category id=\"{}\" name=\"{}\"/>", c.id, FormatXml::escape_xml(&c.name)));
-                        } else {
-                            data.push_str(&format!("<category id=\"{}\" name=\"{}\">{}</category>", c.id, FormatXml::escape_xml(&c.name), ct));
-                        }
-                    }
-                }
-                data.push_str("</categories>");
-                data.shrink_to_fit();
-                Some(data)
-            },
-            None => None,
+    fn header(&mut self, out: &mut Output, _columns: &[&ItemShow<'a>]) -> Option<()> {
+        let mut data = String::with_capacity(256);
+        if self.started {
+            data.push_str(&self.close(None));
+            data.push_str("</categories>");
+        } else if self.categories {
+            // Порожнє дерево категорій: розділ залишається, щоб не змінювати структуру документа
+            data.push_str("<categories></categories>");
         }
+        data.push_str("<products>");
+        out.push(&data)
     }
 
-    fn build_tree_cat(cat: &Vec<Category>, id: u32, capacity: usize) -> String {
-        let mut data = String::with_capacity(capacity);
-        for c in cat {
-            if c.parent_id == id {
-                let ct = FormatXml::build_tree_cat(&cat, c.id, capacity);
-                if ct.len() == 0 {
-                    data.push_str(&format!("<subcategory id=\"{}\" name=\"{}\"/>", c.id, FormatXml::escape_xml(&c.name)));
-                } else {
-                    data.push_str(&format!("<subcategory id=\"{}\" name=\"{}\">{}</subcategory>", c.id, FormatXml::escape_xml(&c.name), ct));
-                }
+    fn row(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()> {
+        let mut data = String::with_capacity(2048);
+        data.push_str("<product");
+        for col in columns {
+            match (col.get)(item) {
+                ValueType::String(v) => data.push_str(&format!(" {}=\"{}\"", FormatXml::escape_xml(col.name), FormatXml::escape_xml(v))),
+                ValueType::Money(v) => data.push_str(&format!(" {}=\"{:.*}\"", FormatXml::escape_xml(col.name), col.decimals, v)),
+                ValueType::Index(v) => data.push_str(&format!(" {}=\"{}\"", FormatXml::escape_xml(col.name), v)),
             }
         }
-        data.shrink_to_fit();
-        data
+        data.push_str("/>");
+        out.push(&data)
     }
 
-}
\ No newline at end of file
+    fn finish(&mut self, out: &mut Output) -> Option<()> {
+        out.push("</products></price>")
+    }
+}
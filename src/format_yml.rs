@@ -0,0 +1,74 @@
+use chrono::Local;
+
+use crate::{price::{PriceItem, ItemShow}, format::{PriceFormatter, Output, SHOP_NAME, SHOP_URL, element}, format_xml::FormatXml, data::Category};
+
+// YML (yml_catalog) для завантаження прайса на Rozetka та Prom.ua.
+// Єдина валюта фіду - гривня, пропозицію без ціни маркетплейс відхиляє, тому вона не вивантажується
+pub struct FormatYML {
+    ua: bool,
+    barcode: bool,                      // Додавати <barcode>, якщо штрихкод входить до колонок прайса
+}
+
+impl FormatYML {
+    pub fn new(ua: bool) -> FormatYML {
+        FormatYML {
+            ua,
+            barcode: false,
+        }
+    }
+}
+
+impl<'a> PriceFormatter<'a> for FormatYML {
+    fn begin(&mut self, out: &mut Output, columns: &[&ItemShow<'a>], _rows: usize) -> Option<()> {
+        self.barcode = columns.iter().any(|col| col.source == "ean");
+        out.push(&format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<yml_catalog date=\"{}\"><shop><name>{}</name><company>{}</company><url>{}</url><currencies><currency id=\"UAH\" rate=\"1\"/></currencies><categories>",
+            Local::now().format("%Y-%m-%d %H:%M"), SHOP_NAME, SHOP_NAME, SHOP_URL))
+    }
+
+    fn category(&mut self, out: &mut Output, category: &Category) -> Option<()> {
+        let name = FormatXml::escape_xml(if self.ua { &category.ua } else { &category.ru });
+        if category.depth == 0 {
+            out.push(&format!("<category id=\"{}\">{}</category>", category.id, name))
+        } else {
+            out.push(&format!("<category id=\"{}\" parentId=\"{}\">{}</category>", category.id, category.parent, name))
+        }
+    }
+
+    fn header(&mut self, out: &mut Output, _columns: &[&ItemShow<'a>]) -> Option<()> {
+        out.push("</categories><offers>")
+    }
+
+    fn row(&mut self, out: &mut Output, _columns: &[&ItemShow<'a>], item: &'a PriceItem) -> Option<()> {
+        if item.price_uah <= 0.0 {
+            return Some(());
+        }
+        let mut data = String::with_capacity(2048);
+        data.push_str(&format!("<offer id=\"{}\" available=\"{}\">", item.id, if item.stock == "1" { "true" } else { "false" }));
+        data.push_str(&format!("<price>{:.2}</price><currencyId>UAH</currencyId><categoryId>{}</categoryId>", item.price_uah, item.category_id));
+        element(&mut data, "name", &item.name);
+        element(&mut data, "vendor", &item.vendor);
+        element(&mut data, "vendorCode", if item.articul.len() > 0 { &item.articul } else { &item.code });
+        element(&mut data, "model", &item.model);
+        element(&mut data, "url", &item.url);
+        // stock_quantity - ціле число, кількість у вигляді діапазону не передається
+        if item.available.parse::<u32>().is_ok() {
+            element(&mut data, "stock_quantity", &item.available);
+        }
+        if self.barcode {
+            element(&mut data, "barcode", &item.ean);
+        }
+        element(&mut data, "description", &item.description);
+        if item.warranty > 0 {
+            data.push_str(&format!("<param name=\"{}\" unit=\"{}\">{}</param>", if self.ua { "Гарантія" } else { "Гарантия" }, if self.ua { "міс" } else { "мес" }, item.warranty));
+        }
+        if item.country.len() > 0 {
+            data.push_str(&format!("<param name=\"{}\">{}</param>", if self.ua { "Країна виробник" } else { "Страна производитель" }, FormatXml::escape_xml(&item.country)));
+        }
+        data.push_str("</offer>");
+        out.push(&data)
+    }
+
+    fn finish(&mut self, out: &mut Output) -> Option<()> {
+        out.push("</offers></shop></yml_catalog>")
+    }
+}
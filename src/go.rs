@@ -1,6 +1,6 @@
 use std::{sync::{Arc, Mutex, mpsc, RwLock}, net::{TcpListener, SocketAddr, TcpStream, Shutdown}, io::{ErrorKind, Read, Write}, time::Duration, thread::{self, JoinHandle}, process};
 
-use crate::{init::Init, log::Log, queue::Queue, worker::{Worker, Message}, cache::Cache};
+use crate::{init::Init, log::Log, queue::Queue, worker::{Worker, Message}, cache::Cache, limit::Limit, audit::Audit, error::Error, flight::Flight, janitor::Janitor, warmup::{Warmup, History}};
 
 pub const MS1: std::time::Duration = Duration::from_millis(1);
 pub const MS1000: std::time::Duration = Duration::from_millis(1000);
@@ -14,6 +14,12 @@ pub struct Go {
     workers: Vec<(Arc<Mutex<Worker>>, mpsc::Sender<Message>)>,          // Потоки обробки даних
     pub use_connection: usize,                                          // Скільки потоків уже запущено
     pub cache: Option<Arc<Mutex<Cache>>>,                                   // Кеш
+    pub limit: Arc<Mutex<Limit>>,                                       // Ліміти запитів та квоти
+    pub flight: Arc<Flight>,                                            // Генерації прайсів, що виконуються
+    pub audit: Option<Arc<Mutex<Audit>>>,                               // Журнал завантажень
+    pub janitor: Option<Arc<Mutex<Janitor>>>,                           // Прибирання каталогу кеша
+    pub history: Arc<Mutex<History>>,                                   // Історія запитів прайсів
+    pub warmup: Option<Arc<Mutex<Warmup>>>,                             // Попередня генерація прайсів
 }
 
 impl Go {
@@ -21,19 +27,20 @@ impl Go {
         let irc = match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], init.irc))){
             Ok(irc) => irc,
             Err(e) => match e.kind() {
-            ErrorKind::PermissionDenied => log.exit(300, ""),
-            ErrorKind::AddrInUse => log.exit(301, ""),
-            ErrorKind::AddrNotAvailable => log.exit(302, ""),
-            _ => log.exit(303, &e.to_string()),
+            ErrorKind::PermissionDenied => log.exit(Error::IrcPermission, ""),
+            ErrorKind::AddrInUse => log.exit(Error::IrcInUse, ""),
+            ErrorKind::AddrNotAvailable => log.exit(Error::IrcNotAvailable, ""),
+            _ => log.exit(Error::IrcBind, &e.to_string()),
             },
         };
 
         if let Err(err) = irc.set_nonblocking(true) {
-            log.exit(304, &err.to_string());
+            log.exit(Error::IrcNonBlocking, &err.to_string());
         }
 
         let queue = Arc::new(Mutex::new(Queue::new(65536))); 
         let max = init.max;
+        let limit = Arc::new(Mutex::new(Limit::new(init.limit_company_hour, init.limit_user_hour, init.limit_burst, init.quota_company_day, init.quota_user_day, init.auth_company_capacity)));
         let init = Arc::new(RwLock::new(init));
         let log = Arc::new(RwLock::new(log));
 
@@ -45,6 +52,12 @@ impl Go {
             workers: Vec::with_capacity(max),
             use_connection: 0,
             cache: None,
+            limit,
+            flight: Flight::new(),
+            audit: None,
+            janitor: None,
+            history: Arc::new(Mutex::new(History::new())),
+            warmup: None,
         }));
         let cache = Cache::new(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
         loop {
@@ -56,20 +69,32 @@ impl Go {
             }
             thread::sleep(MS1000);
         }
+        let audit = Audit::new(Arc::clone(&init), Arc::clone(&log));
+        let janitor = Janitor::new(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
         {
             let mut g = Mutex::lock(&go).unwrap();
             g.cache = Some(cache);
+            g.audit = Some(audit);
+            g.janitor = Some(janitor);
+        }
+        let warmup = Warmup::new(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
+        {
+            let mut g = Mutex::lock(&go).unwrap();
+            g.warmup = Some(warmup);
         }
 
         Go::create_workers(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
         Go::create_sender(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
         Go::create_tcp(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
-        
+        {
+            let l = RwLock::read(&log).unwrap();
+            l.info("server started", None);
+        }
 
-        // Читати irc канал для зупинки
+        // Читати irc канал для зупинки та службових команд
         for stream in irc.incoming() {
             match stream {
-                Ok(mut stream) => if let Ok(_) = Go::set_stop(Arc::clone(&go), &mut stream) {
+                Ok(mut stream) => if let Ok(_) = Go::command(Arc::clone(&go), &mut stream) {
                     break;
                 },
                 Err(err) => match err.kind() {
@@ -80,7 +105,8 @@ impl Go {
         }
     }
 
-    fn set_stop(go: Arc<Mutex<Go>>, stream: &mut TcpStream) -> Result<(), ()> {
+    // Обробка команди з irc сокета, Ok(()) означає зупинку сервера
+    fn command(go: Arc<Mutex<Go>>, stream: &mut TcpStream) -> Result<(), ()> {
         if let Err(_) = stream.set_read_timeout(Some(Duration::from_secs(1))) {
             if let Err(_) = stream.shutdown(Shutdown::Both) { }
             return Err(());
@@ -99,6 +125,16 @@ impl Go {
                         Go::answer_stop(stream);
                         return Ok(());
                     }
+                    if data == b"cache" {
+                        Go::answer_cache(go, stream);
+                        if let Err(_) = stream.shutdown(Shutdown::Both) { }
+                        return Err(());
+                    }
+                    if data == b"limit" {
+                        Go::answer_limit(go, stream);
+                        if let Err(_) = stream.shutdown(Shutdown::Both) { }
+                        return Err(());
+                    }
                     if let Err(_) = stream.shutdown(Shutdown::Both) { }
                     return Err(());
                 },
@@ -111,12 +147,18 @@ impl Go {
         let tcp;
         let sender;
         let cache;
+        let audit;
+        let janitor;
+        let warmup;
         {
             let mut g = Mutex::lock(&go).unwrap();
             g.stop = true;
             tcp = g.tcp.take();
             sender = g.sender.take();
             cache = g.cache.take();
+            audit = g.audit.take();
+            janitor = g.janitor.take();
+            warmup = g.warmup.take();
             for (item, sender) in &g.workers {
                 {
                     let mut w = Mutex::lock(&item).unwrap();
@@ -137,15 +179,59 @@ impl Go {
         if let Some(sender) = sender {
             sender.join().unwrap();
         }
+        if let Some(warmup) = warmup {
+            Warmup::join(warmup);
+        }
         if let Some(cache) = cache {
             Cache::join(cache);
         }
+        // Робочі потоки зупинено, їхні канали журналу закриваються разом з ними
+        {
+            let mut g = Mutex::lock(&go).unwrap();
+            g.workers.clear();
+        }
+        if let Some(audit) = audit {
+            Audit::join(audit);
+        }
+        if let Some(janitor) = janitor {
+            Janitor::join(janitor);
+        }
     }
 
     fn answer_stop(stream: &mut TcpStream) {
         if let Err(_) = stream.write_all(process::id().to_string().as_bytes()) { }
     }
 
+    fn answer_limit(go: Arc<Mutex<Go>>, stream: &mut TcpStream) {
+        let limit;
+        {
+            let g = Mutex::lock(&go).unwrap();
+            limit = Arc::clone(&g.limit);
+        }
+        let report;
+        {
+            let mut l = Mutex::lock(&limit).unwrap();
+            report = l.report();
+        }
+        if let Err(_) = stream.write_all(report.as_bytes()) { }
+    }
+
+    fn answer_cache(go: Arc<Mutex<Go>>, stream: &mut TcpStream) {
+        let janitor;
+        {
+            let g = Mutex::lock(&go).unwrap();
+            janitor = g.janitor.as_ref().map(|j| Arc::clone(j));
+        }
+        let report = match janitor {
+            Some(janitor) => {
+                let j = Mutex::lock(&janitor).unwrap();
+                j.report()
+            },
+            None => "{}".to_owned(),
+        };
+        if let Err(_) = stream.write_all(report.as_bytes()) { }
+    }
+
     fn create_workers(go: Arc<Mutex<Go>>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) {
         let max;
         {
@@ -171,10 +257,10 @@ impl Go {
             let bind = match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], init.port))) {
                 Ok(bind) => bind,
                 Err(err) => match err.kind() {
-                    ErrorKind::PermissionDenied => log.exit(400, ""),
-                    ErrorKind::AddrInUse => log.exit(401, ""),
-                    ErrorKind::AddrNotAvailable => log.exit(402, ""),
-                    _ => log.exit(403, &err.to_string()),
+                    ErrorKind::PermissionDenied => log.exit(Error::TcpPermission, ""),
+                    ErrorKind::AddrInUse => log.exit(Error::TcpInUse, ""),
+                    ErrorKind::AddrNotAvailable => log.exit(Error::TcpNotAvailable, ""),
+                    _ => log.exit(Error::TcpBind, &err.to_string()),
                 }
             };
             match bind.set_nonblocking(true) {
@@ -218,7 +304,7 @@ impl Go {
                         };
                     }
                 },
-                Err(err) => log.exit(404, &err.to_string()),
+                Err(err) => log.exit(Error::TcpNonBlocking, &err.to_string()),
             } 
         });
         {
@@ -262,7 +348,7 @@ impl Go {
                         if let Some(t) = q.take() {
                             tcp = t;
                         } else {
-                            log.exit(500, "");
+                            log.exit(Error::QueueTake, "");
                         }
                     }
                 }
@@ -293,7 +379,7 @@ impl Go {
                             }
                         }
                         if let None = index {
-                            log.exit(501, "");
+                            log.exit(Error::WorkerNotFound, "");
                         }
                     }
                     if let Some(ind) = index {
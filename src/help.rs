@@ -1,3 +1,5 @@
+use crate::error::Error;
+
 pub struct Help {}
 
 impl Help {
@@ -5,13 +7,16 @@ impl Help {
         let desc = "Brain B2B — це високошвидкісна програма FastCGI для WEB-додатків, яка генерує прайс-листи для клієнтів.";
         let ver = format!("brain_b2b версія: {}", env!("CARGO_PKG_VERSION"));
         let help = "
-    Використання: brain_b2b [start|check|stop|help]
+    Використання: brain_b2b [start|check|stop|limit|cache|errors [json]|help]
     
     Дії:
         start       : запуск додатка
         check       : перевірити irc сокет, і якщо він вільний, запустити додаток,
                     : якщо зайнятий, то не створює запис в error.log
         stop        : зупинка додатка з усіма робочими потоками
+        limit       : показати лічильники лімітів запитів та квот генерації (JSON)
+        cache       : показати стан каталогу кеша прайсів (JSON)
+        errors      : показати каталог кодів помилок, errors json - у форматі JSON
         help        : показати цю довідку
     ";
         println!("");
@@ -19,4 +24,12 @@ impl Help {
         println!("{}", ver);
         println!("{}", help);
     }
-}
\ No newline at end of file
+
+    pub fn errors(json: bool) {
+        if json {
+            println!("{}", Error::catalogue_json());
+        } else {
+            print!("{}", Error::catalogue_text());
+        }
+    }
+}
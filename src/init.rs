@@ -1,8 +1,8 @@
-use std::{env, fs::{read_to_string, create_dir}, path::Path};
+use std::{env, fs::{read_to_string, create_dir}, path::Path, collections::HashMap};
 
 use serde_json::Value;
 
-use crate::{log::Log, db::DB};
+use crate::{log::{Log, LogInit, Level}, db::DB, error::Error, message::Messages, meta::PRICE_TTL, column::Columns, param::{Format, PriceVolume}};
 
 #[derive(Debug, PartialEq)]
 pub enum AppInit {
@@ -11,6 +11,9 @@ pub enum AppInit {
     Start,
     Check,
     Stop,
+    Limit,
+    Cache,
+    Errors(bool),
 }
 
 #[derive(Debug)]
@@ -38,6 +41,20 @@ pub struct Init {
     pub dir: String,
     pub salt: String,
 
+    pub limit_company_hour: u32,
+    pub limit_user_hour: u32,
+    pub limit_burst: u32,
+    pub quota_company_day: u32,
+    pub quota_user_day: u32,
+    pub cache_max_mb: u32,
+    pub price_ttl: HashMap<String, i64>,    // Час життя файлу кеша прайса (с) за форматом та обсягом
+    pub warmup: Vec<String>,                // Запити прайсів для прогріву після оновлення даних
+    pub warmup_top: u32,                    // Кількість найпопулярніших запитів з історії для прогріву
+    pub warmup_pause: u32,                  // Пауза між генераціями прогріву (мс)
+    pub audit_file: bool,
+    pub messages: Messages,
+    pub columns: Columns,                   // Каталог колонок прайса, файл columns.json
+
     pub auth_company_capacity: usize,
     pub auth_user_capacity: usize,
     pub country_capacity: usize,
@@ -56,7 +73,7 @@ pub struct Init {
 }
 
 impl Init {
-    pub fn new(log: &Log, dir: &str) -> Init {
+    pub fn new(log: &mut Log, dir: &str) -> Init {
         let mut args = env::args();
         args.next();
         let app = match args.next() {
@@ -66,6 +83,9 @@ impl Init {
             "check" => AppInit::Check,
             "stop" => AppInit::Stop,
             "go" => AppInit::Go,
+            "limit" => AppInit::Limit,
+            "cache" => AppInit::Cache,
+            "errors" => AppInit::Errors(args.next().as_deref() == Some("json")),
             _ => AppInit::Help,
           },
         }; 
@@ -78,173 +98,173 @@ impl Init {
         }
         let cfg = match read_to_string(&file_name) {
           Ok(cfg) => cfg,
-          Err(err) => log.exit(100, &err.to_string()),
+          Err(err) => log.exit(Error::ConfigRead, &err.to_string()),
         };
 
         let val: Value = match serde_json::from_str(&cfg){
             Ok(val) => val,
-            Err(err) => log.exit(101, &err.to_string()),
+            Err(err) => log.exit(Error::ConfigParse, &err.to_string()),
         };
         let port: u16 = match val.get("port") {
             Some(port) => match port.as_u64() {
                 Some(port) => match u16::try_from(port) {
                     Ok(port) => match port {
-                        0 => log.exit(103, ""),
+                        0 => log.exit(Error::PortFormat, ""),
                         port => port,
                     },
-                    Err(err) => log.exit(103, &err.to_string()),
+                    Err(err) => log.exit(Error::PortFormat, &err.to_string()),
                 },
-                None => log.exit(103, ""),
+                None => log.exit(Error::PortFormat, ""),
             },
-            None => log.exit(102, ""),
+            None => log.exit(Error::PortMissing, ""),
         };
         let time_zone: String = match val.get("time_zone") {
             Some(time_zone) => match time_zone.as_str() {
                 Some(time_zone) => time_zone.to_owned(),
-                None => log.exit(105, ""),
+                None => log.exit(Error::TimeZoneFormat, ""),
             },
-            None => log.exit(104, ""),
+            None => log.exit(Error::TimeZoneMissing, ""),
         };
         let max: u8 = match val.get("max_thread") {
             Some(max) => match max.as_u64() {
                 Some(max) => match u8::try_from(max) {
                     Ok(max) => match max {
-                        0 => log.exit(107, ""),
+                        0 => log.exit(Error::MaxFormat, ""),
                         max => max,
                     },
-                    Err(err) => log.exit(107, &err.to_string()),
+                    Err(err) => log.exit(Error::MaxFormat, &err.to_string()),
                 },
-                None => log.exit(107, ""),
+                None => log.exit(Error::MaxFormat, ""),
             },
-            None => log.exit(106, ""),
+            None => log.exit(Error::MaxMissing, ""),
         };
 
         let db_log_host: String = match val.get("db_log_host") {
             Some(host) => match host.as_str() {
                 Some(host) => host.to_owned(),
-                None => log.exit(109, ""),
+                None => log.exit(Error::DbLogHostFormat, ""),
             },
-            None => log.exit(108, ""),
+            None => log.exit(Error::DbLogHostMissing, ""),
         };
         let db_log_port: u16 = match val.get("db_log_port") {
             Some(port) => match port.as_u64() {
                 Some(port) => match u16::try_from(port) {
                     Ok(port) => match port {
-                        0 => log.exit(111, ""),
+                        0 => log.exit(Error::DbLogPortFormat, ""),
                         port => port,
                     },
-                    Err(err) => log.exit(111, &err.to_string()),
+                    Err(err) => log.exit(Error::DbLogPortFormat, &err.to_string()),
                 },
-                None => log.exit(111, ""),
+                None => log.exit(Error::DbLogPortFormat, ""),
             },
-            None => log.exit(110, ""),
+            None => log.exit(Error::DbLogPortMissing, ""),
         };
         let db_log_user: String = match val.get("db_log_user") {
             Some(user) => match user.as_str() {
                 Some(user) => user.to_owned(),
-                None => log.exit(113, ""),
+                None => log.exit(Error::DbLogUserFormat, ""),
             },
-            None => log.exit(112, ""),
+            None => log.exit(Error::DbLogUserMissing, ""),
         };
         let db_log_pwd: String = match val.get("db_log_pwd") {
             Some(pwd) => match pwd.as_str() {
                 Some(pwd) => pwd.to_owned(),
-                None => log.exit(115, ""),
+                None => log.exit(Error::DbLogPwdFormat, ""),
             },
-            None => log.exit(114, ""),
+            None => log.exit(Error::DbLogPwdMissing, ""),
         };
         let db_log_name: String = match val.get("db_log_name") {
             Some(name) => match name.as_str() {
                 Some(name) => name.to_owned(),
-                None => log.exit(117, ""),
+                None => log.exit(Error::DbLogNameFormat, ""),
             },
-            None => log.exit(116, ""),
+            None => log.exit(Error::DbLogNameMissing, ""),
         };
         let db_log = DBInit{host: db_log_host, port: db_log_port, user: db_log_user, pwd: db_log_pwd, name: db_log_name};
 
         let db_b2b_host: String = match val.get("db_b2b_host") {
             Some(host) => match host.as_str() {
                 Some(host) => host.to_owned(),
-                None => log.exit(119, ""),
+                None => log.exit(Error::DbB2bHostFormat, ""),
             },
-            None => log.exit(118, ""),
+            None => log.exit(Error::DbB2bHostMissing, ""),
         };
         let db_b2b_port: u16 = match val.get("db_b2b_port") {
             Some(port) => match port.as_u64() {
                 Some(port) => match u16::try_from(port) {
                     Ok(port) => match port {
-                        0 => log.exit(121, ""),
+                        0 => log.exit(Error::DbB2bPortFormat, ""),
                         port => port,
                     },
-                    Err(err) => log.exit(121, &err.to_string()),
+                    Err(err) => log.exit(Error::DbB2bPortFormat, &err.to_string()),
                 },
-                None => log.exit(121, ""),
+                None => log.exit(Error::DbB2bPortFormat, ""),
             },
-            None => log.exit(120, ""),
+            None => log.exit(Error::DbB2bPortMissing, ""),
         };
         let db_b2b_user: String = match val.get("db_b2b_user") {
             Some(user) => match user.as_str() {
                 Some(user) => user.to_owned(),
-                None => log.exit(123, ""),
+                None => log.exit(Error::DbB2bUserFormat, ""),
             },
-            None => log.exit(122, ""),
+            None => log.exit(Error::DbB2bUserMissing, ""),
         };
         let db_b2b_pwd: String = match val.get("db_b2b_pwd") {
             Some(pwd) => match pwd.as_str() {
                 Some(pwd) => pwd.to_owned(),
-                None => log.exit(125, ""),
+                None => log.exit(Error::DbB2bPwdFormat, ""),
             },
-            None => log.exit(124, ""),
+            None => log.exit(Error::DbB2bPwdMissing, ""),
         };
         let db_b2b_name: String = match val.get("db_b2b_name") {
             Some(name) => match name.as_str() {
                 Some(name) => name.to_owned(),
-                None => log.exit(127, ""),
+                None => log.exit(Error::DbB2bNameFormat, ""),
             },
-            None => log.exit(126, ""),
+            None => log.exit(Error::DbB2bNameMissing, ""),
         };
         let db_b2b = DBInit{host: db_b2b_host, port: db_b2b_port, user: db_b2b_user, pwd: db_b2b_pwd, name: db_b2b_name};
 
         let db_local_host: String = match val.get("db_local_host") {
             Some(host) => match host.as_str() {
                 Some(host) => host.to_owned(),
-                None => log.exit(129, ""),
+                None => log.exit(Error::DbLocalHostFormat, ""),
             },
-            None => log.exit(128, ""),
+            None => log.exit(Error::DbLocalHostMissing, ""),
         };
         let db_local_port: u16 = match val.get("db_local_port") {
             Some(port) => match port.as_u64() {
                 Some(port) => match u16::try_from(port) {
                     Ok(port) => match port {
-                        0 => log.exit(131, ""),
+                        0 => log.exit(Error::DbLocalPortFormat, ""),
                         port => port,
                     },
-                    Err(err) => log.exit(131, &err.to_string()),
+                    Err(err) => log.exit(Error::DbLocalPortFormat, &err.to_string()),
                 },
-                None => log.exit(131, ""),
+                None => log.exit(Error::DbLocalPortFormat, ""),
             },
-            None => log.exit(130, ""),
+            None => log.exit(Error::DbLocalPortMissing, ""),
         };
         let db_local_user: String = match val.get("db_local_user") {
             Some(user) => match user.as_str() {
                 Some(user) => user.to_owned(),
-                None => log.exit(133, ""),
+                None => log.exit(Error::DbLocalUserFormat, ""),
             },
-            None => log.exit(132, ""),
+            None => log.exit(Error::DbLocalUserMissing, ""),
         };
         let db_local_pwd: String = match val.get("db_local_pwd") {
             Some(pwd) => match pwd.as_str() {
                 Some(pwd) => pwd.to_owned(),
-                None => log.exit(135, ""),
+                None => log.exit(Error::DbLocalPwdFormat, ""),
             },
-            None => log.exit(134, ""),
+            None => log.exit(Error::DbLocalPwdMissing, ""),
         };
         let db_local_name: String = match val.get("db_local_name") {
             Some(name) => match name.as_str() {
                 Some(name) => name.to_owned(),
-                None => log.exit(137, ""),
+                None => log.exit(Error::DbLocalNameFormat, ""),
             },
-            None => log.exit(136, ""),
+            None => log.exit(Error::DbLocalNameMissing, ""),
         };
         let db_local = DBInit{host: db_local_host, port: db_local_port, user: db_local_user, pwd: db_local_pwd, name: db_local_name};
 
@@ -252,14 +272,14 @@ impl Init {
             Some(port) => match port.as_u64() {
                 Some(port) => match u16::try_from(port) {
                     Ok(port) => match port {
-                        0 => log.exit(139, ""),
+                        0 => log.exit(Error::IrcFormat, ""),
                         port => port,
                     },
-                    Err(err) => log.exit(139, &err.to_string()),
+                    Err(err) => log.exit(Error::IrcFormat, &err.to_string()),
                 },
-                None => log.exit(139, ""),
+                None => log.exit(Error::IrcFormat, ""),
             },
-            None => log.exit(138, ""),
+            None => log.exit(Error::IrcMissing, ""),
         };
 
         let cache = format!("{}/cache", dir);
@@ -267,27 +287,131 @@ impl Init {
 
         if path.exists() {
             if !path.is_dir() {
-                log.exit(502, &cache);
+                log.exit(Error::CacheNotDir, &cache);
             }
         } else if let Err(err) = create_dir(path) {
-            log.exit(503, &err.to_string());
+            log.exit(Error::CacheCreate, &err.to_string());
         }
 
         let salt: String = match val.get("salt") {
             Some(salt) => match salt.as_str() {
                 Some(salt) => salt.to_owned(),
-                None => log.exit(141, ""),
+                None => log.exit(Error::SaltFormat, ""),
+            },
+            None => log.exit(Error::SaltMissing, ""),
+        };
+
+        let limit_company_hour = Init::get_optional_u32(&val, "limit_company_hour", Error::LimitCompanyHourFormat, log);
+        let limit_user_hour = Init::get_optional_u32(&val, "limit_user_hour", Error::LimitUserHourFormat, log);
+        let limit_burst = Init::get_optional_u32(&val, "limit_burst", Error::LimitBurstFormat, log);
+        let quota_company_day = Init::get_optional_u32(&val, "quota_company_day", Error::QuotaCompanyDayFormat, log);
+        let quota_user_day = Init::get_optional_u32(&val, "quota_user_day", Error::QuotaUserDayFormat, log);
+        let cache_max_mb = Init::get_optional_u32(&val, "cache_max_mb", Error::CacheMaxFormat, log);
+        let mut price_ttl = HashMap::new();
+        if let Some(ttl) = val.get("price_ttl") {
+            match ttl.as_object() {
+                Some(ttl) => for (name, minutes) in ttl {
+                    if !Init::price_ttl_key(name) {
+                        log.exit(Error::PriceTtlFormat, name);
+                    }
+                    match minutes.as_u64() {
+                        Some(minutes) if minutes > 0 => price_ttl.insert(name.to_owned(), minutes as i64 * 60),
+                        _ => log.exit(Error::PriceTtlFormat, name),
+                    };
+                },
+                None => log.exit(Error::PriceTtlFormat, ""),
+            }
+        }
+        let mut warmup = Vec::new();
+        if let Some(list) = val.get("warmup") {
+            match list.as_array() {
+                Some(list) => for query in list {
+                    match query.as_str() {
+                        Some(query) => warmup.push(query.to_owned()),
+                        None => log.exit(Error::WarmupFormat, &query.to_string()),
+                    }
+                },
+                None => log.exit(Error::WarmupFormat, ""),
+            }
+        }
+        let warmup_top = Init::get_optional_u32(&val, "warmup_top", Error::WarmupTopFormat, log);
+        let warmup_pause = Init::get_optional_u32(&val, "warmup_pause", Error::WarmupPauseFormat, log);
+        let audit_file: bool = match val.get("audit_file") {
+            Some(audit_file) => match audit_file.as_bool() {
+                Some(audit_file) => audit_file,
+                None => log.exit(Error::AuditFileFormat, ""),
             },
-            None => log.exit(140, ""),
+            None => false,
         };
 
+        let mut log_init = LogInit::new();
+        if let Some(level) = val.get("log_level") {
+            log_init.level = match level.as_str() {
+                Some(level) => match Level::from_str(level) {
+                    Some(level) => level,
+                    None => log.exit(Error::LogLevelFormat, level),
+                },
+                None => log.exit(Error::LogLevelFormat, ""),
+            };
+        }
+        if let Some(sink) = val.get("log_sink") {
+            match sink.as_array() {
+                Some(sink) => {
+                    log_init.file = false;
+                    log_init.stderr = false;
+                    for s in sink {
+                        match s.as_str() {
+                            Some("file") => log_init.file = true,
+                            Some("stderr") => log_init.stderr = true,
+                            Some("syslog") => log_init.syslog = Some("/dev/log".to_owned()),
+                            _ => log.exit(Error::LogSinkFormat, &s.to_string()),
+                        }
+                    }
+                },
+                None => log.exit(Error::LogSinkFormat, ""),
+            };
+        }
+        if let Some(syslog) = val.get("log_syslog") {
+            match syslog.as_str() {
+                Some(syslog) => if log_init.syslog.is_some() {
+                    log_init.syslog = Some(syslog.to_owned());
+                },
+                None => log.exit(Error::LogSyslogFormat, ""),
+            };
+        }
+        if let Some(max_size) = val.get("log_max_size") {
+            log_init.max_size = match max_size.as_u64() {
+                Some(max_size) => max_size,
+                None => log.exit(Error::LogMaxSizeFormat, ""),
+            };
+        }
+        if let Some(daily) = val.get("log_daily") {
+            log_init.daily = match daily.as_bool() {
+                Some(daily) => daily,
+                None => log.exit(Error::LogDailyFormat, ""),
+            };
+        }
+        if let Some(keep) = val.get("log_keep") {
+            log_init.keep = match keep.as_u64() {
+                Some(keep) => match usize::try_from(keep) {
+                    Ok(keep) => keep,
+                    Err(err) => log.exit(Error::LogKeepFormat, &err.to_string()),
+                },
+                None => log.exit(Error::LogKeepFormat, ""),
+            };
+        }
+        log.set(log_init);
+        let log: &Log = log;
+        let messages = Messages::new(dir, log);
+        let columns = Columns::new(dir, log);
+
         let mut db = match DB::simple(&db_b2b) {
             Some(db) => db,
-            None => log.exit(180, ""),
+            None => log.exit(Error::InitB2bConnect, ""),
         };
         let mut dbl = match DB::simple(&db_log) {
             Some(db) => db,
-            None => log.exit(180, ""),
+            None => log.exit(Error::InitLogConnect, ""),
         };
 
         let mut auth_company_capacity: usize = 0;
@@ -304,9 +428,7 @@ impl Init {
         let mut category_capacity: usize = 0;
 
         if app == AppInit::Go {
-            if cfg!(debug_assertions) {
-                println!("{} Start load init", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string());
-            }
+            let _span = log.span("load init", None);
             let sql = "
                 SELECT count(DISTINCT u.companyID)
                 FROM users u INNER JOIN companies c ON c.companyID=u.companyID
@@ -322,10 +444,10 @@ impl Init {
                         Some(val) => {
                             auth_company_capacity = 100 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
+                None => log.exit(Error::InitB2bQuery, sql),
             }
 
             let sql = "
@@ -346,10 +468,10 @@ impl Init {
                         Some(val) => {
                             auth_user_capacity = 5 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
+                None => log.exit(Error::InitB2bQuery, sql),
             }
 
             let sql = "
@@ -362,10 +484,10 @@ impl Init {
                         Some(val) => {
                             country_capacity = 5 + *val as usize;
                         },
-                        None => log.exit(183, sql),
+                        None => log.exit(Error::InitLogQuery, sql),
                     };
                 },
-                None => log.exit(183, sql),
+                None => log.exit(Error::InitLogQuery, sql),
             }
 
             let sql = "
@@ -384,10 +506,10 @@ impl Init {
                         Some(val) => {
                             target_capacity = 20 + *val as usize;
                         },
-                        None => log.exit(183, sql),
+                        None => log.exit(Error::InitLogQuery, sql),
                     };
                 },
-                None => log.exit(183, sql),
+                None => log.exit(Error::InitLogQuery, sql),
             }
 
             let sql = "
@@ -405,10 +527,10 @@ impl Init {
                         Some(val) => {
                             lock_capacity = 100 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
+                None => log.exit(Error::InitB2bQuery, sql),
             }
 
             let sql = "
@@ -429,10 +551,10 @@ impl Init {
                         Some(val) => {
                             lock_item_capacity = 100 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
+                None => log.exit(Error::InitB2bQuery, sql),
             }
 
             let sql = "
@@ -449,10 +571,10 @@ impl Init {
                         Some(val) => {
                             product_capacity = 10000 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
+                None => log.exit(Error::InitB2bQuery, sql),
             }
 
             let sql = "
@@ -465,10 +587,10 @@ impl Init {
                         Some(val) => {
                             bonus_company_capacity = 100 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
+                None => log.exit(Error::InitB2bQuery, sql),
             }
 
             let sql = "
@@ -481,10 +603,10 @@ impl Init {
                         Some(val) => {
                             bonus_group_capacity = 5 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
+                None => log.exit(Error::InitB2bQuery, sql),
             }
 
             let sql = "
@@ -499,10 +621,10 @@ impl Init {
                         Some(val) => {
                             stock_capacity = 5 + *val as usize;
                         },
-                        None => log.exit(183, sql),
+                        None => log.exit(Error::InitLogQuery, sql),
                     };
                 },
-                None => log.exit(183, sql),
+                None => log.exit(Error::InitLogQuery, sql),
             }
 
             let sql = "
@@ -528,10 +650,10 @@ impl Init {
                         Some(val) => {
                             stock_product_capacity = 10000 + *val as usize;
                         },
-                        None => log.exit(183, sql),
+                        None => log.exit(Error::InitLogQuery, sql),
                     };
                 },
-                None => log.exit(183, sql),
+                None => log.exit(Error::InitLogQuery, sql),
             }
 
             let sql = "
@@ -544,19 +666,30 @@ impl Init {
                         Some(val) => {
                             category_capacity = 20 + *val as usize;
                         },
-                        None => log.exit(181, sql),
+                        None => log.exit(Error::InitB2bQuery, sql),
                     };
                 },
-                None => log.exit(181, sql),
-            }
-            if cfg!(debug_assertions) {
-                println!("{} Finish load init", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string());
+                None => log.exit(Error::InitB2bQuery, sql),
             }
         }
 
         Init {
             app, port, irc, time_zone, max: max.into(), db_log, db_b2b, db_local, dir: dir.to_owned(), salt,
 
+            limit_company_hour,
+            limit_user_hour,
+            limit_burst,
+            quota_company_day,
+            quota_user_day,
+            cache_max_mb,
+            price_ttl,
+            warmup,
+            warmup_top,
+            warmup_pause,
+            audit_file,
+            messages,
+            columns,
+
             auth_company_capacity,
             auth_user_capacity,
             country_capacity,
@@ -574,4 +707,55 @@ impl Init {
             small_buffer_capacity: 32768, 
         }
     }
-}
\ No newline at end of file
+
+    // Час життя прайса: формат_обсяг, обсяг, формат, default, інакше 30 хвилин
+    pub fn price_ttl(&self, format: &str, volume: &str) -> i64 {
+        let keys = [format!("{}_{}", format, volume), volume.to_owned(), format.to_owned(), "default".to_owned()];
+        for key in &keys {
+            if let Some(ttl) = self.price_ttl.get(key) {
+                return *ttl;
+            }
+        }
+        PRICE_TTL
+    }
+
+    // Ключ price_ttl: default, формат, обсяг або формат_обсяг
+    fn price_ttl_key(name: &str) -> bool {
+        let volume = |name: &str| PriceVolume::ALL.iter().any(|v| v.as_str() == name);
+        if name == "default" || Format::from_str(name).is_some() || volume(name) {
+            return true;
+        }
+        match name.split_once('_') {
+            Some((format, name)) => Format::from_str(format).is_some() && volume(name),
+            None => false,
+        }
+    }
+
+    // Необов'язковий числовий параметр, відсутній параметр означає 0 (без обмежень)
+    fn get_optional_u32(val: &Value, name: &str, err: Error, log: &Log) -> u32 {
+        match val.get(name) {
+            Some(v) => match v.as_u64() {
+                Some(v) => match u32::try_from(v) {
+                    Ok(v) => v,
+                    Err(e) => log.exit(err, &e.to_string()),
+                },
+                None => log.exit(err, ""),
+            },
+            None => 0,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_ttl_keys() {
+        for name in ["default", "xlsx", "ndjson", "full", "full_uah", "xlsx_full", "json_full_uah"] {
+            assert!(Init::price_ttl_key(name), "{}", name);
+        }
+        for name in ["", "xslx_full", "xlsx_", "_full", "full_xlsx", "xlsx_full_", "Default"] {
+            assert!(!Init::price_ttl_key(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::{sync::{Arc, Mutex, RwLock}, thread::{self, JoinHandle}, time::{Duration, SystemTime}, collections::HashMap, fs::{read_dir, remove_file, metadata}};
+
+use chrono::Local;
+
+use crate::{go::Go, init::Init, log::Log, meta::{PriceMeta, PRICE_TTL}};
+
+pub const MS1000: std::time::Duration = Duration::from_millis(1000);
+pub const JANITOR_PERIOD: u64 = 60;                  // Інтервал прибирання (с)
+pub const TMP_AGE: u64 = 3600;                       // Вік незавершеного .tmp файлу (с)
+pub const SIDECAR: [&str; 4] = ["meta", "gz", "zst", "br"];
+
+// Файл кеша прайса разом з метаданими та стисненими варіантами
+#[derive(Debug, Default)]
+struct Entry {
+    main: bool,                         // Існує основний файл
+    files: Vec<String>,
+    bytes: u64,
+    used: Option<SystemTime>,           // Останнє використання (mtime файлу .meta)
+}
+
+// Стан каталогу кеша після останнього прибирання
+#[derive(Debug, Default)]
+pub struct Usage {
+    pub time: String,
+    pub prices: usize,
+    pub files: usize,
+    pub bytes: u64,
+    pub expired: usize,                 // Видалено застарілих прайсів
+    pub tmp: usize,                     // Видалено незавершених .tmp файлів
+    pub orphan: usize,                  // Видалено файлів без основного файлу
+    pub evicted: usize,                 // Видалено через перевищення розміру
+}
+
+// Прибирання каталогу cache у фоновому потоці
+#[derive(Debug)]
+pub struct Janitor {
+    thread: Option<JoinHandle<()>>,
+    pub usage: Usage,
+}
+
+impl Janitor {
+    pub fn new(go: Arc<Mutex<Go>>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Arc<Mutex<Janitor>> {
+        let janitor = Arc::new(Mutex::new(Janitor {
+            thread: None,
+            usage: Usage::default(),
+        }));
+        let janitor_thread = Arc::clone(&janitor);
+        let thread = thread::spawn(move || {
+            let mut wait = 0;
+            loop {
+                {
+                    let g = Mutex::lock(&go).unwrap();
+                    if g.stop {
+                        break;
+                    }
+                }
+                if wait == 0 {
+                    let usage = Janitor::sweep(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
+                    let mut j = Mutex::lock(&janitor_thread).unwrap();
+                    j.usage = usage;
+                    wait = JANITOR_PERIOD;
+                }
+                thread::sleep(MS1000);
+                wait -= 1;
+            }
+        });
+        {
+            let mut j = Mutex::lock(&janitor).unwrap();
+            j.thread.replace(thread);
+        }
+        janitor
+    }
+
+    pub fn join(janitor: Arc<Mutex<Janitor>>) {
+        let thread;
+        {
+            let mut j = Mutex::lock(&janitor).unwrap();
+            thread = j.thread.take();
+        }
+        if let Some(thread) = thread {
+            thread.join().unwrap();
+        }
+    }
+
+    fn sweep(go: Arc<Mutex<Go>>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Usage {
+        let generation = Janitor::generation(go);
+        let dir;
+        let max_size;
+        {
+            let i = RwLock::read(&init).unwrap();
+            dir = format!("{}/cache", i.dir);
+            max_size = u64::from(i.cache_max_mb) * 1024 * 1024;
+        }
+        let log = RwLock::read(&log).unwrap();
+        let _span = log.span("cache janitor", None);
+
+        let mut usage = Usage::default();
+        usage.time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let items = match read_dir(&dir) {
+            Ok(items) => items,
+            Err(_) => return usage,
+        };
+        let now = SystemTime::now();
+        let mut list: HashMap<String, Entry> = HashMap::new();
+        for item in items {
+            let item = match item {
+                Ok(item) => item,
+                Err(_) => continue,
+            };
+            let m = match item.metadata() {
+                Ok(m) if m.is_file() => m,
+                _ => continue,
+            };
+            let name = item.file_name().to_string_lossy().to_string();
+            let path = format!("{}/{}", dir, name);
+            let age = match m.modified() {
+                Ok(modified) => now.duration_since(modified).unwrap_or_default().as_secs(),
+                Err(_) => 0,
+            };
+
+            // Файл, який генерується зараз, або залишився після аварійної зупинки
+            if name.ends_with(".tmp") {
+                if age > TMP_AGE && Janitor::remove(&path) {
+                    usage.tmp += 1;
+                } else {
+                    usage.files += 1;
+                    usage.bytes += m.len();
+                }
+                continue;
+            }
+            let (base, main) = match name.rsplit_once('.') {
+                Some((base, ext)) if SIDECAR.contains(&ext) => (base.to_owned(), false),
+                _ => (name.clone(), true),
+            };
+            if !base.starts_with("price_") {
+                continue;
+            }
+            let e = list.entry(base).or_default();
+            if main {
+                e.main = true;
+            }
+            if name.ends_with(".meta") {
+                e.used = m.modified().ok();
+            }
+            e.files.push(path);
+            e.bytes += m.len();
+        }
+
+        let mut live: Vec<(String, Entry)> = Vec::with_capacity(list.len());
+        for (base, e) in list {
+            if !e.main {
+                // Метадані записуються перед публікацією файлу прайса, свіжі ще не є сиротами
+                if e.used.map_or(false, |used| now.duration_since(used).unwrap_or_default().as_secs() < JANITOR_PERIOD) {
+                    continue;
+                }
+                for f in &e.files {
+                    if Janitor::remove(f) {
+                        usage.orphan += 1;
+                    }
+                }
+                continue;
+            }
+            let file = format!("{}/{}", dir, base);
+            let expired = match PriceMeta::read(&file) {
+                // Прайс побудований з даних, які вже замінені новим завантаженням Cache
+                Some(meta) => meta.expired() || generation.map_or(false, |g| meta.generation != g),
+                // Файл без метаданих або старого формату
+                None => match metadata(&file).and_then(|m| m.modified()) {
+                    Ok(modified) => now.duration_since(modified).unwrap_or_default().as_secs() as i64 > PRICE_TTL,
+                    Err(_) => true,
+                },
+            };
+            if expired {
+                if PriceMeta::remove(&file) {
+                    usage.expired += 1;
+                }
+                continue;
+            }
+            usage.prices += 1;
+            usage.files += e.files.len();
+            usage.bytes += e.bytes;
+            live.push((file, e));
+        }
+
+        // Перевищено розмір кеша: видаляються прайси, які найдовше не використовувались
+        if max_size > 0 && usage.bytes > max_size {
+            live.sort_by_key(|(_, e)| e.used.unwrap_or(SystemTime::UNIX_EPOCH));
+            for (file, e) in live {
+                if usage.bytes <= max_size {
+                    break;
+                }
+                if PriceMeta::remove(&file) {
+                    usage.evicted += 1;
+                    usage.prices -= 1;
+                    usage.files -= e.files.len();
+                    usage.bytes -= e.bytes;
+                }
+            }
+        }
+        usage
+    }
+
+    // Поточне покоління даних Cache, None - дані ще не завантажені
+    fn generation(go: Arc<Mutex<Go>>) -> Option<u64> {
+        let cache;
+        {
+            let g = Mutex::lock(&go).unwrap();
+            cache = Arc::clone(g.cache.as_ref()?);
+        }
+        let c = Mutex::lock(&cache).unwrap();
+        match c.generation {
+            0 => None,
+            generation => Some(generation),
+        }
+    }
+
+    fn remove(file: &str) -> bool {
+        match remove_file(file) {
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    pub fn report(&self) -> String {
+        let u = &self.usage;
+        format!("{{\"time\":\"{}\",\"prices\":{},\"files\":{},\"bytes\":{},\"expired\":{},\"tmp\":{},\"orphan\":{},\"evicted\":{}}}",
+            u.time, u.prices, u.files, u.bytes, u.expired, u.tmp, u.orphan, u.evicted)
+    }
+}
@@ -0,0 +1,188 @@
+use std::{collections::{HashMap, hash_map::Entry}, time::Instant};
+
+use chrono::{Local, Datelike, Timelike};
+
+// Лічильник запитів компанії або користувача
+#[derive(Debug)]
+pub struct Counter {
+    tokens: f64,                        // Залишок токенів
+    last: Instant,                      // Час останнього поповнення
+    day: i32,                           // День, за який рахуємо генерації
+    pub generated: u32,                 // Кількість генерацій за день
+    pub requests: u64,                  // Кількість прийнятих запитів
+    pub rejected: u64,                  // Кількість відхилених запитів
+}
+
+impl Counter {
+    pub fn new(tokens: f64, day: i32) -> Counter {
+        Counter {
+            tokens,
+            last: Instant::now(),
+            day,
+            generated: 0,
+            requests: 0,
+            rejected: 0,
+        }
+    }
+
+    // Поповнити токени, повертає час очікування одного токену (сек.)
+    fn refill(&mut self, rate: u32, burst: u32) -> u64 {
+        if rate == 0 {
+            return 0;
+        }
+        let per_sec = rate as f64 / 3600.0;
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * per_sec).min(burst as f64);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            0
+        } else {
+            ((1.0 - self.tokens) / per_sec).ceil() as u64
+        }
+    }
+
+    fn set_day(&mut self, day: i32) {
+        if self.day != day {
+            self.day = day;
+            self.generated = 0;
+        }
+    }
+}
+
+// Обмеження запитів та квоти генерацій
+#[derive(Debug)]
+pub struct Limit {
+    company_rate: u32,                  // Запитів на годину для компанії
+    user_rate: u32,                     // Запитів на годину для користувача
+    burst: u32,                         // Максимальна кількість запитів підряд
+    company_quota: u32,                 // Генерацій на добу для компанії
+    user_quota: u32,                    // Генерацій на добу для користувача
+    company: HashMap<u32, Counter>,
+    user: HashMap<u32, Counter>,
+}
+
+impl Limit {
+    pub fn new(company_rate: u32, user_rate: u32, burst: u32, company_quota: u32, user_quota: u32, cap: usize) -> Limit {
+        Limit {
+            company_rate,
+            user_rate,
+            burst,
+            company_quota,
+            user_quota,
+            company: HashMap::with_capacity(cap),
+            user: HashMap::with_capacity(cap),
+        }
+    }
+
+    fn capacity(&self, rate: u32) -> u32 {
+        if self.burst == 0 { rate } else { self.burst }
+    }
+
+    fn today() -> i32 {
+        Local::now().num_days_from_ce()
+    }
+
+    // Перевірити ліміт запитів, у разі перевищення повертає Retry-After (сек.)
+    pub fn check_rate(&mut self, company_id: u32, user_id: u32) -> Result<(), u64> {
+        let day = Limit::today();
+        let company_cap = self.capacity(self.company_rate);
+        let user_cap = self.capacity(self.user_rate);
+        let company = match self.company.entry(company_id) {
+            Entry::Occupied(o) => o.into_mut(),
+            Entry::Vacant(v) => v.insert(Counter::new(company_cap as f64, day)),
+        };
+        let company_wait = company.refill(self.company_rate, company_cap);
+        let user = match self.user.entry(user_id) {
+            Entry::Occupied(o) => o.into_mut(),
+            Entry::Vacant(v) => v.insert(Counter::new(user_cap as f64, day)),
+        };
+        let user_wait = user.refill(self.user_rate, user_cap);
+
+        let wait = company_wait.max(user_wait);
+        if wait > 0 {
+            user.rejected += 1;
+            self.company.get_mut(&company_id).unwrap().rejected += 1;
+            return Err(wait);
+        }
+        if self.user_rate > 0 {
+            user.tokens -= 1.0;
+        }
+        user.requests += 1;
+        let company = self.company.get_mut(&company_id).unwrap();
+        if self.company_rate > 0 {
+            company.tokens -= 1.0;
+        }
+        company.requests += 1;
+        Ok(())
+    }
+
+    // Перевірити денну квоту генерацій, у разі перевищення повертає Retry-After (сек.)
+    pub fn check_quota(&mut self, company_id: u32, user_id: u32) -> Result<(), u64> {
+        let day = Limit::today();
+        let mut over = false;
+        if let Some(c) = self.company.get_mut(&company_id) {
+            c.set_day(day);
+            if self.company_quota > 0 && c.generated >= self.company_quota {
+                over = true;
+            }
+        }
+        if let Some(u) = self.user.get_mut(&user_id) {
+            u.set_day(day);
+            if self.user_quota > 0 && u.generated >= self.user_quota {
+                over = true;
+            }
+        }
+        if over {
+            if let Some(c) = self.company.get_mut(&company_id) {
+                c.rejected += 1;
+            }
+            if let Some(u) = self.user.get_mut(&user_id) {
+                u.rejected += 1;
+            }
+            let now = Local::now();
+            return Err(86400 - now.num_seconds_from_midnight() as u64);
+        }
+        Ok(())
+    }
+
+    // Зарахувати генерацію прайсу
+    pub fn add_generation(&mut self, company_id: u32, user_id: u32) {
+        let day = Limit::today();
+        if let Some(c) = self.company.get_mut(&company_id) {
+            c.set_day(day);
+            c.generated += 1;
+        }
+        if let Some(u) = self.user.get_mut(&user_id) {
+            u.set_day(day);
+            u.generated += 1;
+        }
+    }
+
+    // Звіт по лічильникам для сокета IRC
+    pub fn report(&mut self) -> String {
+        let day = Limit::today();
+        let mut data = String::with_capacity(64 * (self.company.len() + self.user.len() + 1));
+        data.push_str(&format!("{{\"company_hour\":{},\"user_hour\":{},\"burst\":{},\"company_day\":{},\"user_day\":{},\"company\":{{", self.company_rate, self.user_rate, self.burst, self.company_quota, self.user_quota));
+        let company_cap = self.capacity(self.company_rate);
+        for (company_id, c) in &mut self.company {
+            c.set_day(day);
+            c.refill(self.company_rate, company_cap);
+            data.push_str(&format!("\"{}\":{{\"tokens\":{:.2},\"generated\":{},\"requests\":{},\"rejected\":{}}},", company_id, c.tokens, c.generated, c.requests, c.rejected));
+        }
+        if !self.company.is_empty() {
+            data.pop();
+        }
+        data.push_str("},\"user\":{");
+        let user_cap = self.capacity(self.user_rate);
+        for (user_id, u) in &mut self.user {
+            u.set_day(day);
+            u.refill(self.user_rate, user_cap);
+            data.push_str(&format!("\"{}\":{{\"tokens\":{:.2},\"generated\":{},\"requests\":{},\"rejected\":{}}},", user_id, u.tokens, u.generated, u.requests, u.rejected));
+        }
+        if !self.user.is_empty() {
+            data.pop();
+        }
+        data.push_str("}}");
+        data
+    }
+}
@@ -1,11 +1,124 @@
-use std::{process, fs::OpenOptions, io::Write};
+use std::{process, fs::{File, OpenOptions, rename, remove_file, metadata}, io::Write, sync::{Mutex, atomic::{AtomicU64, Ordering}}, time::Instant, os::unix::net::UnixDatagram, path::Path};
 
-use chrono::Local;
+use chrono::{Local, DateTime, Datelike};
+
+use crate::error::{Error, Severity};
+
+static REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+// Рівень журналу
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    pub fn from_str(level: &str) -> Option<Level> {
+        match level {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+
+    // Рівень syslog (RFC 5424)
+    fn severity(&self) -> u8 {
+        match self {
+            Level::Error => 3,
+            Level::Warn => 4,
+            Level::Info => 6,
+            Level::Debug => 7,
+        }
+    }
+}
+
+// Контекст запиту для записів журналу
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogCtx {
+    pub request: u64,                   // Номер запиту
+    pub company: u32,                   // companyID
+}
+
+impl LogCtx {
+    pub fn new() -> LogCtx {
+        LogCtx {
+            request: REQUEST_ID.fetch_add(1, Ordering::Relaxed) + 1,
+            company: 0,
+        }
+    }
+}
+
+// Налаштування журналу
+#[derive(Debug, Clone)]
+pub struct LogInit {
+    pub level: Level,                   // Мінімальний рівень записів
+    pub file: bool,                     // Писати в файл error.log
+    pub stderr: bool,                   // Писати в stderr
+    pub syslog: Option<String>,         // Сокет syslog, наприклад /dev/log
+    pub max_size: u64,                  // Ротація при перевищенні розміру (байт), 0 - без ротації
+    pub daily: bool,                    // Щоденна ротація
+    pub keep: usize,                    // Скільки архівних файлів зберігати
+}
+
+impl LogInit {
+    pub fn new() -> LogInit {
+        LogInit {
+            level: Level::Info,
+            file: true,
+            stderr: true,
+            syslog: None,
+            max_size: 0,
+            daily: false,
+            keep: 7,
+        }
+    }
+}
+
+// Відкритий файл журналу
+#[derive(Debug)]
+struct LogFile {
+    file: Option<File>,
+    size: u64,
+    day: i32,
+}
+
+// Вимір тривалості операції, записується на рівні debug при завершенні
+pub struct Span<'a> {
+    log: &'a Log,
+    name: &'static str,
+    ctx: Option<LogCtx>,
+    start: Option<Instant>,
+}
+
+impl<'a> Drop for Span<'a> {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            let ms = start.elapsed().as_secs_f64() * 1000.0;
+            self.log.event(Level::Debug, None, &format!("finish {} ({:.3} ms)", self.name, ms), self.ctx.as_ref());
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Log {
     pid: u32,
     file: String,
+    set: LogInit,
+    out: Mutex<LogFile>,
 }
 
 impl Log {
@@ -13,152 +126,156 @@ impl Log {
         Log {
             pid: process::id(),
             file: format!("{}/error.log", dir),
+            set: LogInit::new(),
+            out: Mutex::new(LogFile { file: None, size: 0, day: 0 }),
         }
     }
 
-    pub fn write(&self, err: u32, text: &str) {
-        
-        let time = Local::now().format("%Y.%m.%d %H:%M:%S%.9f %:z").to_string();
-        let str;
-        if text.len() > 0 {
-            str = format!("{} PID={} Помилка {}: {}. Опис: {}\n", time, self.pid, err, self.get_error(err), text);
-        } else {
-            str = format!("{} PID={} Помилка {}: {}.\n", time, self.pid, err, self.get_error(err));
-        };
-        eprint!("{}", &str);
-        if let Ok(mut file) = OpenOptions::new().create(true).write(true).append(true).open(&self.file) {
-            file.write_all(str.as_bytes()).unwrap();
+    // Застосувати налаштування з файлу конфігурації
+    pub fn set(&mut self, set: LogInit) {
+        self.set = set;
+        let mut out = Mutex::lock(&self.out).unwrap();
+        out.file = None;
+    }
+
+    pub fn write(&self, err: Error, text: &str) {
+        let level = match err.severity() {
+            Severity::Fatal | Severity::Error => Level::Error,
+            Severity::Warn => Level::Warn,
+            Severity::Client => Level::Info,
         };
+        self.event(level, Some(err), text, None);
+    }
+
+    pub fn info(&self, text: &str, ctx: Option<&LogCtx>) {
+        self.event(Level::Info, None, text, ctx);
+    }
+
+    pub fn debug(&self, text: &str, ctx: Option<&LogCtx>) {
+        self.event(Level::Debug, None, text, ctx);
+    }
+
+    pub fn span(&self, name: &'static str, ctx: Option<&LogCtx>) -> Span<'_> {
+        if self.set.level < Level::Debug {
+            return Span { log: self, name, ctx: None, start: None };
+        }
+        self.event(Level::Debug, None, &format!("start {}", name), ctx);
+        Span { log: self, name, ctx: ctx.copied(), start: Some(Instant::now()) }
     }
 
-    pub fn exit(&self, err: u32, text: &str) -> ! {
+    pub fn event(&self, level: Level, err: Option<Error>, text: &str, ctx: Option<&LogCtx>) {
+        if level > self.set.level {
+            return;
+        }
+        let mut str = String::with_capacity(256 + text.len());
+        str.push_str(&format!("{{\"time\":\"{}\",\"pid\":{},\"level\":\"{}\"", Local::now().format("%Y-%m-%dT%H:%M:%S%.6f%:z"), self.pid, level.as_str()));
+        if let Some(err) = err {
+            str.push_str(&format!(",\"code\":{},\"message\":\"{}\"", err.code(), Log::escape(err.message())));
+        }
+        if text.len() > 0 {
+            str.push_str(&format!(",\"text\":\"{}\"", Log::escape(text)));
+        }
+        if let Some(ctx) = ctx {
+            str.push_str(&format!(",\"request\":{}", ctx.request));
+            if ctx.company > 0 {
+                str.push_str(&format!(",\"company\":{}", ctx.company));
+            }
+        }
+        str.push_str("}\n");
+
+        if self.set.stderr {
+            eprint!("{}", &str);
+        }
+        if self.set.file {
+            self.write_file(&str);
+        }
+        if let Some(socket) = &self.set.syslog {
+            // facility user (1)
+            let msg = format!("<{}>brain_b2b[{}]: {}", 8 + level.severity(), self.pid, str.trim_end());
+            if let Ok(sock) = UnixDatagram::unbound() {
+                if let Err(_) = sock.send_to(msg.as_bytes(), socket) { }
+            }
+        }
+    }
+
+    pub fn exit(&self, err: Error, text: &str) -> ! {
         self.write(err, text);
         process::exit(1);
     }
 
-    fn get_error(&self, err: u32) -> String {
-        match err {
-            100 => "Відсутній файл конфігурації".to_owned(),
-            101 => "Файл конфігурації має невірний формат".to_owned(),
-            102 => "В файлі конфігурації відсутній параметр 'port' (Порт TCP запуску сервера генерації прайсів B2B)".to_owned(),
-            103 => "В файлі конфігурації параметр 'port' має невірний формат (Число від 1 до 65536)".to_owned(),
-            104 => "В файлі конфігурації відсутній параметр 'time_zone'".to_owned(),
-            105 => "В файлі конфігурації параметр 'time_zone' має невірний формат (https://en.wikipedia.org/wiki/List_of_tz_database_time_zones)".to_owned(),
-            106 => "В файлі конфігурації відсутній параметр 'max' (Кількість потоків на обробку прайсів)".to_owned(),
-            107 => "В файлі конфігурації параметр 'max' має невірний формат (Число від 1 до 255)".to_owned(),
-            108 => "В файлі конфігурації відсутній параметр 'db_log_host' (Хост бази даних MySql логістики товарів)".to_owned(),
-            109 => "В файлі конфігурації параметр 'db_log_host' має невірний формат".to_owned(),
-            110 => "В файлі конфігурації відсутній параметр 'db_log_port' (Порт бази даних MySql логістики товарів)".to_owned(),
-            111 => "В файлі конфігурації параметр 'db_log_port' має невірний формат (Число від 1 до 65536)".to_owned(),
-            112 => "В файлі конфігурації відсутній параметр 'db_log_user' (Користувач бази даних MySql логістики товарів)".to_owned(),
-            113 => "В файлі конфігурації параметр 'db_log_user' має невірний формат".to_owned(),
-            114 => "В файлі конфігурації відсутній параметр 'db_log_pwd' (Пароль користувача бази даних MySql логістики товарів)".to_owned(),
-            115 => "В файлі конфігурації параметр 'db_log_pwd' має невірний формат".to_owned(),
-            116 => "В файлі конфігурації відсутній параметр 'db_log_name' (Назва бази даних MySql логістики товарів)".to_owned(),
-            117 => "В файлі конфігурації параметр 'db_log_name' має невірний формат".to_owned(),
-            118 => "В файлі конфігурації відсутній параметр 'db_b2b_host' (Хост бази даних MySql B2B портала)".to_owned(),
-            119 => "В файлі конфігурації параметр 'db_b2b_host' має невірний формат".to_owned(),
-            120 => "В файлі конфігурації відсутній параметр 'db_b2b_port' (Порт бази даних MySql B2B портала)".to_owned(),
-            121 => "В файлі конфігурації параметр 'db_b2b_port' має невірний формат (Число від 1 до 65536)".to_owned(),
-            122 => "В файлі конфігурації відсутній параметр 'db_b2b_user' (Користувач бази даних MySql B2B портала)".to_owned(),
-            123 => "В файлі конфігурації параметр 'db_b2b_user' має невірний формат".to_owned(),
-            124 => "В файлі конфігурації відсутній параметр 'db_b2b_pwd' (Пароль користувача бази даних MySql B2B портала)".to_owned(),
-            125 => "В файлі конфігурації параметр 'db_b2b_pwd' має невірний формат".to_owned(),
-            126 => "В файлі конфігурації відсутній параметр 'db_b2b_name' (Назва бази даних MySql B2B портала)".to_owned(),
-            127 => "В файлі конфігурації параметр 'db_b2b_name' має невірний формат".to_owned(),
-            128 => "В файлі конфігурації відсутній параметр 'db_local_host' (Хост бази даних MySql логіювання запитів)".to_owned(),
-            129 => "В файлі конфігурації параметр 'db_local_host' має невірний формат".to_owned(),
-            130 => "В файлі конфігурації відсутній параметр 'db_local_port' (Порт бази даних MySql логіювання запитів)".to_owned(),
-            131 => "В файлі конфігурації параметр 'db_local_port' має невірний формат (Число від 1 до 65536)".to_owned(),
-            132 => "В файлі конфігурації відсутній параметр 'db_local_user' (Користувач бази даних MySql логіювання запитів)".to_owned(),
-            133 => "В файлі конфігурації параметр 'db_local_user' має невірний формат".to_owned(),
-            134 => "В файлі конфігурації відсутній параметр 'db_local_pwd' (Пароль користувача бази даних MySql логіювання запитів)".to_owned(),
-            135 => "В файлі конфігурації параметр 'db_local_pwd' має невірний формат".to_owned(),
-            136 => "В файлі конфігурації відсутній параметр 'db_local_name' (Назва бази даних MySql логіювання запитів)".to_owned(),
-            137 => "В файлі конфігурації параметр 'db_local_name' має невірний формат".to_owned(),
-            138 => "В файлі конфігурації відсутній параметр 'irc' (Порт TCP управління сервера генерації прайсів B2B)".to_owned(),
-            139 => "В файлі конфігурації параметр 'irc' має невірний формат (Число від 1 до 65536)".to_owned(),
-            140 => "В файлі конфігурації відсутній параметр 'salt'".to_owned(),
-            141 => "В файлі конфігурації параметр 'salt' має невірний формат".to_owned(),
-            
-            180 => "Неможливо встановити з'єднання з базою даних B2B".to_owned(),
-            181 => "Помилка запиту з базою даних B2B".to_owned(),
-            182 => "Неможливо встановити з'єднання з базою даних B2B".to_owned(),
-            183 => "Помилка запиту з базою даних B2B".to_owned(),
-
-            200 => "Помилка запуска сервера".to_owned(),
-            201 => "Помилка при з’єднання до IRC сервера".to_owned(),
-            202 => "Не вдалося приднатися до IRC сервера".to_owned(),
-            203 => "Помилка відправлення сигналу до IRC сервера".to_owned(),
-            204 => "Не вдалося встановити timeout читання даних від IRC сервера".to_owned(),
-            205 => "Не вдалося прочитати дані від IRC сервера".to_owned(),
-            206 => "Дані відсутні при читанні від IRC сервера".to_owned(),
-            207 => "Невірні дані отримані при читанні від IRC сервера".to_owned(),
-            208 => "Невірні дані отримані при читанні від IRC сервера".to_owned(),
-      
-            300 => "Відсутні права для запуска IRC сервера".to_owned(),
-            301 => "Сокет IRC занятий".to_owned(),
-            302 => "Сокет IRC недоступний для користування".to_owned(),
-            303 => "Неможливо відкрити сокет IRC".to_owned(),
-            304 => "Неможливо встановити неблокуючий режим для сокет IRC".to_owned(),
-
-            400 => "Відсутні права для запуска TCP сервера".to_owned(),
-            401 => "Сокет TCP занятий".to_owned(),
-            402 => "Сокет TCP недоступний для користування".to_owned(),
-            403 => "Неможливо відкрити сокет TCP".to_owned(),
-            404 => "Неможливо встановити неблокуючий режим для сокет TCP".to_owned(),
-
-            500 => "Проблеми з чергою потоків".to_owned(),
-            501 => "Переплутані з'єднання".to_owned(),
-            502 => "Неможливо використовувати каталог для кеша прайсів".to_owned(),
-            503 => "Неможливо створити каталог для кеша прайсів".to_owned(),
-
-            600 => "Некорректна строка підключення до бази даних".to_owned(),
-            601 => "Помилка з'єднання з базою даних".to_owned(),
-            602 => "Помилка виконання запиту до бази даних".to_owned(),
-            603 => "Не вдалося встановити початкові параметри підключення".to_owned(),
-
-            _ => "Невідома помилка".to_owned(),
-        }
-    }
-
-    pub fn client_err(&self, err: u32) -> String {
-        let err = match err {
-            1 => "Помилка 1: Відсутній параметр format".to_owned(),
-            2 => "Помилка 2: Формат прайс-листа не підтримується".to_owned(),
-            3 => "Помилка 3: Відсутній параметр full".to_owned(),
-            4 => "Помилка 4: Тип прайсу не підтримується".to_owned(),
-            5 => "Помилка 5: Відсутній параметр companyID".to_owned(),
-            6 => "Помилка 6: Невірний формат companyID".to_owned(),
-            7 => "Помилка 7: Відсутній параметр targetID".to_owned(),
-            8 => "Помилка 8: Невірний формат targetID".to_owned(),
-            9 => "Помилка 9: Відсутній параметр lang".to_owned(),
-            10 => "Помилка 10: Невірний формат lang".to_owned(),
-            11 => "Помилка 11: Відсутній параметр time".to_owned(),
-            12 => "Помилка 12: Невірний формат time".to_owned(),
-            13 => "Помилка 13: Відсутній параметр userID".to_owned(),
-            14 => "Помилка 14: Невірний формат userID".to_owned(),
-            15 => "Помилка 15: Відсутній параметр token".to_owned(),
-            16 => "Помилка 16: Невірний token".to_owned(),
-            17 => "Помилка 17: Доступ заборонено (companyID не знайдено)".to_owned(),
-            18 => "Помилка 18: Доступ заборонено (userID не знайдено)".to_owned(),
-            19 => "Помилка 19: Доступ заборонено (profilesID не встановлено)".to_owned(),
-            20 => "Помилка 20: Неможливо перевірити директорію cache для прайсів".to_owned(),
-            21 => "Помилка 21: Неможливо видалити застарілі cache-файли для прайсів".to_owned(),
-            22 => "Помилка 22: Неможливо видалити cache-файли для прайсів".to_owned(),
-            23 => "Помилка 23: targetID повинен бути > 0".to_owned(),
-            24 => "Помилка 24: Сервер прайсів зупинено".to_owned(),
-            25 => "Помилка 25: Невідомий targetID".to_owned(),
-            26 => "Помилка 26: До targetID не прив'язан склад".to_owned(),
-            27 => "Помилка 27: Неможливо отримати ціни".to_owned(),
-            28 => "Помилка 28: Неможливо прочитати ціни".to_owned(),
-            29 => "Помилка 29: Неможливо створити xlsx файл".to_owned(),
-            30 => "Помилка 30: Неможливо створити php файл".to_owned(),
-            31 => "Помилка 31: Неможливо створити xml файл".to_owned(),
-            32 => "Помилка 31: Неможливо створити json файл".to_owned(),
-            
-            _ => "Невідома помилка".to_owned(),
-        };
-        format!("<!DOCTYPE HTML><html><head><title>PriceList</title><meta charset=\"utf-8\"/></head><body>{}</body></html>", err)
+    fn escape(val: &str) -> String {
+        let mut new = String::with_capacity(val.len() + 16);
+        for c in val.chars() {
+            match c {
+                '"' => new.push_str("\\\""),
+                '\\' => new.push_str("\\\\"),
+                '\n' => new.push_str("\\n"),
+                '\r' => new.push_str("\\r"),
+                '\t' => new.push_str("\\t"),
+                c if (c as u32) < 0x20 => new.push_str(&format!("\\u{:04x}", c as u32)),
+                c => new.push(c),
+            }
+        }
+        new
     }
-}
\ No newline at end of file
+
+    fn write_file(&self, str: &str) {
+        let mut out = Mutex::lock(&self.out).unwrap();
+        let day = Local::now().num_days_from_ce();
+        if out.file.is_some() {
+            let by_size = self.set.max_size > 0 && out.size + str.len() as u64 > self.set.max_size;
+            let by_day = self.set.daily && out.day != day;
+            if by_size || by_day {
+                out.file = None;
+                self.rotate();
+            }
+        }
+        if out.file.is_none() {
+            // Файл, що залишився з попереднього дня після перезапуску
+            if self.set.daily {
+                if let Ok(m) = metadata(&self.file) {
+                    if let Ok(modified) = m.modified() {
+                        if DateTime::<Local>::from(modified).num_days_from_ce() != day {
+                            self.rotate();
+                        }
+                    }
+                }
+            }
+            match OpenOptions::new().create(true).append(true).open(&self.file) {
+                Ok(file) => {
+                    out.size = match metadata(&self.file) {
+                        Ok(m) => m.len(),
+                        Err(_) => 0,
+                    };
+                    out.file = Some(file);
+                },
+                Err(_) => return,
+            }
+            out.day = day;
+        }
+        if let Some(file) = &mut out.file {
+            if let Ok(_) = file.write_all(str.as_bytes()) {
+                out.size += str.len() as u64;
+            }
+        }
+    }
+
+    // error.log -> error.log.1 -> error.log.2 ... останній файл видаляється
+    fn rotate(&self) {
+        if self.set.keep == 0 {
+            if let Err(_) = remove_file(&self.file) { }
+            return;
+        }
+        let last = format!("{}.{}", self.file, self.set.keep);
+        if Path::new(&last).exists() {
+            if let Err(_) = remove_file(&last) { }
+        }
+        for i in (1..self.set.keep).rev() {
+            let from = format!("{}.{}", self.file, i);
+            if Path::new(&from).exists() {
+                if let Err(_) = rename(&from, format!("{}.{}", self.file, i + 1)) { }
+            }
+        }
+        if let Err(_) = rename(&self.file, format!("{}.1", self.file)) { }
+    }
+}
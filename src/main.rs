@@ -11,10 +11,27 @@ mod cache;
 mod db;
 mod data;
 mod param;
+mod format;
 mod format_xlsx;
 mod format_php;
 mod format_xml;
 mod format_json;
+mod format_csv;
+mod format_yml;
+mod format_gmc;
+mod format_cml;
+mod format_ndjson;
+mod limit;
+mod audit;
+mod error;
+mod message;
+mod column;
+mod response;
+mod compress;
+mod flight;
+mod meta;
+mod janitor;
+mod warmup;
 
 use std::env;
 
@@ -27,8 +44,8 @@ use crate::{log::Log, init::Init};
 
 fn main() {
     let dir = env::current_dir().unwrap().to_str().unwrap().to_owned();
-    let log = Log::new(&dir);
-    let init = Init::new(&log, &dir);
+    let mut log = Log::new(&dir);
+    let init = Init::new(&mut log, &dir);
 
     match init.app {
         AppInit::Help => Help::show(),
@@ -36,6 +53,9 @@ fn main() {
         AppInit::Check => Action::start(init, log, &dir, true),
         AppInit::Go => Go::run(init, log),
         AppInit::Stop => Action::stop(init, log),
+        AppInit::Limit => Action::limit(init, log),
+        AppInit::Cache => Action::cache(init, log),
+        AppInit::Errors(json) => Help::errors(json),
     }
     
 }
\ No newline at end of file
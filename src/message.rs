@@ -0,0 +1,115 @@
+use std::{collections::HashMap, fs::read_to_string, path::Path};
+
+use serde_json::Value;
+
+use crate::{log::Log, error::Error};
+
+pub const LANG_DEFAULT: &str = "ua";
+
+// Переклад повідомлень для однієї мови
+#[derive(Debug)]
+struct Translation {
+    title: String,                      // "Помилка"
+    errors: HashMap<u32, String>,       // Код помилки -> текст
+}
+
+// Повідомлення клієнту про помилки, файл errors.json
+//
+// {
+//     "ua": {"title": "Помилка", "errors": {"1": "Відсутній параметр format", ...}},
+//     "en": {"title": "Error", "errors": {"1": "Missing parameter format", ...}}
+// }
+#[derive(Debug)]
+pub struct Messages {
+    list: HashMap<String, Translation>,
+}
+
+impl Messages {
+    pub fn new(dir: &str, log: &Log) -> Messages {
+        let mut list = HashMap::new();
+        let file_name = format!("{}/errors.json", dir);
+        if !Path::new(&file_name).exists() {
+            return Messages { list };
+        }
+        let val: Value = match read_to_string(&file_name) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(val) => val,
+                Err(err) => log.exit(Error::MessagesFormat, &err.to_string()),
+            },
+            Err(err) => log.exit(Error::MessagesFormat, &err.to_string()),
+        };
+        let langs = match val.as_object() {
+            Some(langs) => langs,
+            None => log.exit(Error::MessagesFormat, ""),
+        };
+        for (lang, item) in langs {
+            let title = match item.get("title").and_then(|t| t.as_str()) {
+                Some(title) => title.to_owned(),
+                None => log.exit(Error::MessagesFormat, &format!("{}.title", lang)),
+            };
+            let items = match item.get("errors").and_then(|e| e.as_object()) {
+                Some(items) => items,
+                None => log.exit(Error::MessagesFormat, &format!("{}.errors", lang)),
+            };
+            let mut errors = HashMap::with_capacity(items.len());
+            for (code, text) in items {
+                let code: u32 = match code.parse() {
+                    Ok(code) => code,
+                    Err(_) => log.exit(Error::MessagesFormat, &format!("{}.errors.{}", lang, code)),
+                };
+                match text.as_str() {
+                    Some(text) => errors.insert(code, text.to_owned()),
+                    None => log.exit(Error::MessagesFormat, &format!("{}.errors.{}", lang, code)),
+                };
+            }
+            list.insert(lang.clone(), Translation { title, errors });
+        }
+        Messages { list }
+    }
+
+    pub fn has(&self, lang: &str) -> bool {
+        lang == LANG_DEFAULT || self.list.contains_key(lang)
+    }
+
+    // Текст помилки мовою клієнта, якщо перекладу немає - українською
+    pub fn text(&self, lang: &str, err: Error) -> String {
+        if let Some(t) = self.list.get(lang) {
+            if let Some(text) = t.errors.get(&err.code()) {
+                return text.clone();
+            }
+        }
+        err.message().to_owned()
+    }
+
+    // Текст помилки з заголовком та кодом: "Помилка 1: ..."
+    pub fn get(&self, lang: &str, err: Error) -> String {
+        let title = match self.list.get(lang) {
+            Some(t) if t.errors.contains_key(&err.code()) => &t.title as &str,
+            _ => "Помилка",
+        };
+        format!("{} {}: {}", title, err.code(), self.text(lang, err))
+    }
+
+    // Мова повідомлень: параметр lang, потім заголовок Accept-Language
+    pub fn lang(&self, query: Option<&String>, accept: Option<&String>) -> String {
+        if let Some(lang) = query {
+            if self.has(lang) {
+                return lang.clone();
+            }
+        }
+        if let Some(accept) = accept {
+            for item in accept.split(',') {
+                let tag = item.split(';').next().unwrap_or("").trim().to_lowercase();
+                let primary = tag.split('-').next().unwrap_or("");
+                let lang = match primary {
+                    "uk" => "ua",
+                    _ => primary,
+                };
+                if lang.len() > 0 && self.has(lang) {
+                    return lang.to_owned();
+                }
+            }
+        }
+        LANG_DEFAULT.to_owned()
+    }
+}
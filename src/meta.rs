@@ -0,0 +1,90 @@
+use std::{fs::{read_to_string, write, rename, remove_file, File}, time::SystemTime};
+
+use chrono::Local;
+
+use serde_json::{Value, json};
+use sha2::{Sha256, Digest};
+
+use crate::compress::Encoding;
+
+pub const PRICE_TTL: i64 = 30 * 60;                 // Час життя файлу кеша за замовчуванням (с)
+
+// Метадані файлу кеша прайса, зберігаються поруч з файлом в {file}.meta
+#[derive(Debug)]
+pub struct PriceMeta {
+    pub key: String,                    // Канонічний ключ кеша
+    pub created: i64,                   // Час генерації (unix)
+    pub generation: u64,                // Покоління даних Cache, з яких побудовано прайс
+    pub ttl: i64,                       // Час життя (с)
+    pub etag: String,                   // Хеш вмісту в лапках
+    pub rows: Option<usize>,            // Кількість товарів, для сторінок JSON API без підрахунку рядків
+}
+
+impl PriceMeta {
+    pub fn name(file: &str) -> String {
+        format!("{}.meta", file)
+    }
+
+    // ETag вмісту файлу: хеш в лапках
+    pub fn etag(text: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(text);
+        format!("\"{:x}\"", hasher.finalize())
+    }
+
+    pub fn expired(&self) -> bool {
+        Local::now().timestamp() - self.created >= self.ttl
+    }
+
+    // Позначити використання прайса для витіснення найстаріших при переповненні кеша
+    pub fn touch(file: &str) {
+        if let Ok(f) = File::options().write(true).open(PriceMeta::name(file)) {
+            if let Err(_) = f.set_modified(SystemTime::now()) { }
+        }
+    }
+
+    pub fn read(file: &str) -> Option<PriceMeta> {
+        let text = read_to_string(PriceMeta::name(file)).ok()?;
+        let val: Value = serde_json::from_str(&text).ok()?;
+        Some(PriceMeta {
+            key: val.get("key")?.as_str()?.to_owned(),
+            created: val.get("created")?.as_i64()?,
+            generation: val.get("generation")?.as_u64()?,
+            etag: val.get("etag")?.as_str()?.to_owned(),
+            ttl: val.get("ttl").and_then(|v| v.as_i64()).unwrap_or(PRICE_TTL),
+            rows: val.get("rows").and_then(|v| v.as_u64()).map(|v| v as usize),
+        })
+    }
+
+    pub fn write(&self, file: &str) -> bool {
+        let name = PriceMeta::name(file);
+        let tmp = format!("{}.tmp", name);
+        let val = json!({
+            "key": self.key,
+            "created": self.created,
+            "generation": self.generation,
+            "etag": self.etag,
+            "ttl": self.ttl,
+            "rows": self.rows,
+        });
+        if let Err(_) = write(&tmp, val.to_string()) {
+            return false;
+        }
+        match rename(&tmp, &name) {
+            Ok(_) => true,
+            Err(_) => false,
+        }
+    }
+
+    // Видалити файл кеша прайса разом з метаданими та стисненими варіантами
+    pub fn remove(file: &str) -> bool {
+        if let Err(_) = remove_file(PriceMeta::name(file)) { }
+        for encoding in Encoding::ALL {
+            if let Err(_) = remove_file(encoding.file(file)) { }
+        }
+        match remove_file(file) {
+            Ok(_) => true,
+            Err(err) => err.kind() == std::io::ErrorKind::NotFound,
+        }
+    }
+}
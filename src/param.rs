@@ -1,16 +1,376 @@
-use std::{collections::HashMap, sync::{Arc, RwLock}};
+use std::{collections::{HashMap, HashSet}, sync::{Arc, RwLock}};
 
 use sha2::{Sha512, Digest};
-use urlencoding::decode;
+use chrono::Local;
+use urlencoding::{decode, encode};
 
-use crate::{log::Log, init::Init};
+use crate::{init::Init, error::Error, data::Product, price::PriceItem, format::PriceFormatter, format_xlsx::FormatXLSX, format_xml::FormatXml, format_json::FormatJSON, format_php::FormatPHP, format_csv::FormatCSV, format_yml::FormatYML, format_gmc::FormatGMC, format_cml::FormatCML, format_ndjson::FormatNDJSON};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Format {
     XLSX,
     XML,
     JSON,
     PHP,
+    CSV,
+    TSV,
+    YML,
+    GMC,
+    CML,
+    NDJSON,
+}
+
+impl Format {
+    pub fn from_str(format: &str) -> Option<Format> {
+        match format {
+            "xlsx" => Some(Format::XLSX),
+            "xml" => Some(Format::XML),
+            "json" => Some(Format::JSON),
+            "php" => Some(Format::PHP),
+            "csv" => Some(Format::CSV),
+            "tsv" => Some(Format::TSV),
+            "yml" => Some(Format::YML),
+            "gmc" => Some(Format::GMC),
+            "cml" => Some(Format::CML),
+            "ndjson" => Some(Format::NDJSON),
+            _ => None,
+        }
+    }
+
+    // Новий формат реєструється тут: назва в from_str, тип відповіді, помилка створення та PriceFormatter
+    pub fn formatter<'a>(&self, param: &Param) -> Box<dyn PriceFormatter<'a> + 'a> {
+        match self {
+            Format::XLSX => Box::new(FormatXLSX::new()),
+            Format::XML => Box::new(FormatXml::new(param.volume == PriceVolume::Local || param.volume == PriceVolume::Full, matches!(param.lang, Lang::UA))),
+            Format::JSON => Box::new(FormatJSON::new()),
+            Format::PHP => Box::new(FormatPHP::new()),
+            Format::CSV | Format::TSV => Box::new(FormatCSV::new(param.csv.unwrap(), matches!(param.lang, Lang::UA))),
+            Format::YML => Box::new(FormatYML::new(matches!(param.lang, Lang::UA))),
+            Format::GMC => Box::new(FormatGMC::new(matches!(param.lang, Lang::UA))),
+            Format::CML => Box::new(FormatCML::new(matches!(param.lang, Lang::UA))),
+            Format::NDJSON => Box::new(FormatNDJSON::new()),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::XLSX => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            Format::XML => "application/xml",
+            Format::JSON => "application/json",
+            Format::PHP => "application/vnd.php.serialized",
+            Format::CSV => "text/csv; charset=utf-8",
+            Format::TSV => "text/tab-separated-values; charset=utf-8",
+            Format::YML | Format::GMC => "application/xml",
+            Format::CML => "application/zip",
+            Format::NDJSON => "application/x-ndjson",
+        }
+    }
+
+    pub fn error(&self) -> Error {
+        match self {
+            Format::XLSX => Error::XlsxCreate,
+            Format::XML => Error::XmlCreate,
+            Format::JSON => Error::JsonCreate,
+            Format::PHP => Error::PhpCreate,
+            Format::CSV | Format::TSV => Error::CsvCreate,
+            Format::YML => Error::YmlCreate,
+            Format::GMC => Error::GmcCreate,
+            Format::CML => Error::CmlCreate,
+            Format::NDJSON => Error::NdjsonCreate,
+        }
+    }
+
+    // Файл вже стиснутий, Content-Encoding не застосовується
+    pub fn archive(&self) -> bool {
+        *self == Format::XLSX || *self == Format::CML
+    }
+}
+
+pub const PAGE_LIMIT: usize = 1000;     // Розмір сторінки JSON API за замовчуванням
+pub const PAGE_MAX: usize = 10000;
+
+// Сторінка JSON API (format=json з limit, offset або cursor).
+// Сторінки вирізаються з кешованого NDJSON того ж прайса
+#[derive(Debug, Clone, Copy)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: usize,
+    pub generation: Option<u64>,        // Покоління Cache з курсора, сторінки іншого покоління не узгоджені між собою
+}
+
+impl Page {
+    fn new(format: Format, param: &HashMap<String, String>) -> Result<Option<Page>, Error> {
+        let limit = param.get("limit");
+        let offset = param.get("offset");
+        let cursor = param.get("cursor");
+        if limit.is_none() && offset.is_none() && cursor.is_none() {
+            return Ok(None);
+        }
+        if format != Format::JSON {
+            return Err(Error::PageUnsupported);
+        }
+        let limit = match limit {
+            Some(limit) => match limit.parse::<usize>() {
+                Ok(limit) if limit > 0 && limit <= PAGE_MAX => limit,
+                _ => return Err(Error::PageUnsupported),
+            },
+            None => PAGE_LIMIT,
+        };
+        let (offset, generation) = match (offset, cursor) {
+            (Some(_), Some(_)) => return Err(Error::PageUnsupported),
+            (Some(offset), None) => match offset.parse::<usize>() {
+                Ok(offset) => (offset, None),
+                Err(_) => return Err(Error::PageUnsupported),
+            },
+            (None, Some(cursor)) => match cursor.split_once('.').map(|(g, o)| (g.parse::<u64>(), o.parse::<usize>())) {
+                Some((Ok(generation), Ok(offset))) => (offset, Some(generation)),
+                _ => return Err(Error::PageUnsupported),
+            },
+            (None, None) => (0, None),
+        };
+        Ok(Some(Page { offset, limit, generation }))
+    }
+
+    // Курсор виданий для інших даних Cache, зокрема до перезапуску сервера
+    pub fn stale(&self, generation: u64) -> bool {
+        self.generation.map_or(false, |cursor| cursor != generation)
+    }
+
+    // Курсор наступної сторінки: покоління Cache та зсув
+    pub fn cursor(generation: u64, offset: usize) -> String {
+        format!("{}.{}", generation, offset)
+    }
+}
+
+pub const LOOKUP_MAX: usize = 1000;     // Максимальна кількість товарів в одному запиті
+
+// Вибрані товари (product - коди товарів productID, code - коди товарів на сайті) без файлу кеша, лише JSON та XML
+#[derive(Debug, Clone, Default)]
+pub struct Lookup {
+    pub ids: Vec<u32>,
+    pub codes: Vec<String>,
+}
+
+impl Lookup {
+    fn new(format: Format, param: &HashMap<String, String>) -> Result<Option<Lookup>, Error> {
+        let product = param.get("product");
+        let code = param.get("code");
+        if product.is_none() && code.is_none() {
+            return Ok(None);
+        }
+        if format != Format::JSON && format != Format::XML {
+            return Err(Error::LookupUnsupported);
+        }
+        let mut lookup = Lookup::default();
+        if let Some(product) = product {
+            for id in product.split(',').map(|id| id.trim()).filter(|id| id.len() > 0) {
+                match id.parse::<u32>() {
+                    Ok(id) => lookup.ids.push(id),
+                    Err(_) => return Err(Error::LookupUnsupported),
+                }
+            }
+        }
+        if let Some(code) = code {
+            lookup.codes.extend(code.split(',').map(|c| c.trim()).filter(|c| c.len() > 0).map(|c| c.to_owned()));
+        }
+        let count = lookup.ids.len() + lookup.codes.len();
+        if count == 0 || count > LOOKUP_MAX {
+            return Err(Error::LookupUnsupported);
+        }
+        Ok(Some(lookup))
+    }
+}
+
+// Фільтр асортименту прайса. Порожній фільтр пропускає всі товари
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub category: Vec<u32>,             // Категорії разом з підкатегоріями
+    pub vendor: Vec<String>,            // Назви виробників в нижньому регістрі
+    pub vendor_id: Vec<u32>,
+    pub group: Vec<u32>,
+    pub class: Vec<u32>,
+    pub stock: bool,                    // Лише товари в наявності
+    pub delivery: Option<u32>,          // Максимальна кількість днів до поставки
+    pub price_min: Option<f32>,         // Ціна в валюті прайса (грн для full=3, інакше USD)
+    pub price_max: Option<f32>,
+}
+
+impl Filter {
+    fn new(param: &HashMap<String, String>) -> Result<Filter, Error> {
+        let mut filter = Filter::default();
+        filter.category = Filter::ids(param.get("category"))?;
+        filter.group = Filter::ids(param.get("group"))?;
+        filter.class = Filter::ids(param.get("class"))?;
+        if let Some(vendor) = param.get("vendor") {
+            for v in vendor.split(',').map(|v| v.trim()).filter(|v| v.len() > 0) {
+                match v.parse::<u32>() {
+                    Ok(id) => filter.vendor_id.push(id),
+                    Err(_) => filter.vendor.push(v.to_lowercase()),
+                }
+            }
+            filter.vendor_id.sort();
+            filter.vendor_id.dedup();
+            filter.vendor.sort();
+            filter.vendor.dedup();
+        }
+        filter.stock = match param.get("stock").map(|s| s.as_str()) {
+            Some("1") => true,
+            Some("0") | None => false,
+            Some(_) => return Err(Error::FilterFormat),
+        };
+        filter.delivery = match param.get("delivery") {
+            Some(days) => match days.parse::<u32>() {
+                Ok(days) => Some(days),
+                Err(_) => return Err(Error::FilterFormat),
+            },
+            None => None,
+        };
+        filter.price_min = Filter::parse_price(param.get("price_min"))?;
+        filter.price_max = Filter::parse_price(param.get("price_max"))?;
+        Ok(filter)
+    }
+
+    fn ids(val: Option<&String>) -> Result<Vec<u32>, Error> {
+        let mut ids = Vec::new();
+        if let Some(val) = val {
+            for id in val.split(',').map(|id| id.trim()).filter(|id| id.len() > 0) {
+                match id.parse::<u32>() {
+                    Ok(id) => ids.push(id),
+                    Err(_) => return Err(Error::FilterFormat),
+                }
+            }
+        }
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    fn parse_price(val: Option<&String>) -> Result<Option<f32>, Error> {
+        match val {
+            Some(price) => match price.replace(",", ".").parse::<f32>() {
+                Ok(price) if price.is_finite() && price >= 0.0 => Ok(Some(price)),
+                _ => Err(Error::FilterFormat),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.query().len() == 0
+    }
+
+    // Канонічний рядок фільтра для ключа кеша та рядка запиту, порожній без фільтра
+    pub fn query(&self) -> String {
+        let join = |ids: &Vec<u32>| ids.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(",");
+        let mut query: Vec<String> = Vec::new();
+        if self.category.len() > 0 {
+            query.push(format!("category={}", join(&self.category)));
+        }
+        if self.vendor.len() > 0 || self.vendor_id.len() > 0 {
+            let mut vendor = self.vendor.iter().map(|v| encode(v).into_owned()).collect::<Vec<String>>();
+            vendor.extend(self.vendor_id.iter().map(|id| id.to_string()));
+            query.push(format!("vendor={}", vendor.join(",")));
+        }
+        if self.group.len() > 0 {
+            query.push(format!("group={}", join(&self.group)));
+        }
+        if self.class.len() > 0 {
+            query.push(format!("class={}", join(&self.class)));
+        }
+        if self.stock {
+            query.push("stock=1".to_owned());
+        }
+        if let Some(days) = self.delivery {
+            query.push(format!("delivery={}", days));
+        }
+        if let Some(price) = self.price_min {
+            query.push(format!("price_min={}", price));
+        }
+        if let Some(price) = self.price_max {
+            query.push(format!("price_max={}", price));
+        }
+        query.join("&")
+    }
+
+    // Фільтр за даними товару з Cache, до розрахунку цін. categories - вибрані категорії з підкатегоріями
+    pub fn product(&self, p: &Product, categories: &HashSet<u32>) -> bool {
+        (self.category.len() == 0 || categories.contains(&p.category_id))
+            && ((self.vendor.len() == 0 && self.vendor_id.len() == 0) || self.vendor_id.contains(&p.vendor_id) || self.vendor.contains(&p.vendor.to_lowercase()))
+            && (self.group.len() == 0 || self.group.contains(&p.group_id))
+            && (self.class.len() == 0 || self.class.contains(&p.class_id))
+    }
+
+    // Фільтр за наявністю та терміном поставки
+    pub fn supply(&self, item: &PriceItem) -> bool {
+        if item.stock == "1" {
+            return true;
+        }
+        if self.stock {
+            return false;
+        }
+        match self.delivery {
+            Some(days) => item.day_delivery.parse::<u32>().map_or(false, |day| day <= days),
+            None => true,
+        }
+    }
+
+    // Фільтр за ціною, після розрахунку цін
+    pub fn price(&self, price: f32) -> bool {
+        self.price_min.map_or(true, |min| price >= min) && self.price_max.map_or(true, |max| price <= max)
+    }
+}
+
+// Параметри CSV та TSV
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: char,
+    pub quote_all: bool,                // Брати в лапки всі текстові поля, інакше лише ті, що містять роздільник, лапки або перенос рядка
+    pub decimal_comma: bool,            // Десятковий роздільник - кома
+    pub bom: bool,                      // UTF-8 BOM для Excel
+}
+
+impl CsvOptions {
+    // Для TSV роздільник завжди табуляція і лапки не використовуються, для інших форматів параметри не читаються
+    fn new(format: Format, lang: &Lang, param: &HashMap<String, String>) -> Result<Option<CsvOptions>, Error> {
+        if format != Format::CSV && format != Format::TSV {
+            return Ok(None);
+        }
+        let decimal_comma = match param.get("decimal").map(|d| d.as_str()) {
+            Some("comma") => true,
+            Some("point") => false,
+            Some(_) => return Err(Error::CsvOptionUnsupported),
+            None => matches!(lang, Lang::UA),
+        };
+        let delimiter = match (format, param.get("delimiter").map(|d| d.as_str())) {
+            (Format::TSV, _) => '\t',
+            (_, Some("comma")) => ',',
+            (_, Some("semicolon")) => ';',
+            (_, Some("pipe")) => '|',
+            (_, Some(_)) => return Err(Error::CsvOptionUnsupported),
+            (_, None) => if decimal_comma { ';' } else { ',' },
+        };
+        // Ціни не беруться в лапки, кома в ціні зсунула б колонки
+        if decimal_comma && delimiter == ',' {
+            return Err(Error::CsvOptionUnsupported);
+        }
+        let quote_all = match param.get("quote").map(|q| q.as_str()) {
+            Some("all") => format != Format::TSV,
+            Some("minimal") | None => false,
+            Some(_) => return Err(Error::CsvOptionUnsupported),
+        };
+        let bom = match param.get("bom") {
+            Some(bom) => bom == "1",
+            None => false,
+        };
+        Ok(Some(CsvOptions { delimiter, quote_all, decimal_comma, bom }))
+    }
+
+    // Значення для ключа кеша та рядка запиту
+    pub fn query(&self) -> String {
+        format!("delimiter={}&quote={}&decimal={}&bom={}",
+            match self.delimiter { ',' => "comma", '|' => "pipe", '\t' => "tab", _ => "semicolon" },
+            if self.quote_all { "all" } else { "minimal" }, if self.decimal_comma { "comma" } else { "point" }, self.bom as u8)
+    }
 }
 
 pub enum Lang {
@@ -26,6 +386,47 @@ pub enum PriceVolume {
     FullUAH,
 }
 
+impl PriceVolume {
+    // Назва обсягу в параметрі конфігурації price_ttl
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceVolume::Local => "local",
+            PriceVolume::Full => "full",
+            PriceVolume::Short => "short",
+            PriceVolume::FullUAH => "full_uah",
+        }
+    }
+
+    pub const ALL: [PriceVolume; 4] = [PriceVolume::Local, PriceVolume::Full, PriceVolume::Short, PriceVolume::FullUAH];
+}
+
+// Порядок рядків прайса
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    Category,           // Дерево категорій, виробник, назва
+    Code,               // Код товару
+    Price,              // Ціна за зростанням
+}
+
+impl Sort {
+    pub fn from_str(sort: &str) -> Option<Sort> {
+        match sort {
+            "category" => Some(Sort::Category),
+            "code" => Some(Sort::Code),
+            "price" => Some(Sort::Price),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Sort::Category => "category",
+            Sort::Code => "code",
+            Sort::Price => "price",
+        }
+    }
+}
+
 pub struct Param {
     // time: u32,
     pub user_id: u32,
@@ -46,29 +447,29 @@ pub struct Param {
     pub round: bool,
     pub ean: bool,
     pub api: bool,
-
-    // log: Arc<RwLock<Log>>,
+    pub sort: Sort,
+    pub csv: Option<CsvOptions>,        // Лише для CSV та TSV
+    pub page: Option<Page>,
+    pub filter: Filter,
+    pub lookup: Option<Lookup>,
+    pub fields: Vec<String>,            // Вибрані колонки в порядку виводу, порожній - колонки за обсягом прайса
 }
 
 impl Param {
 
-    pub fn new(param: &HashMap<String, String>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Result<Param, String> {
-        let log_read = RwLock::read(&log).unwrap();
+    pub fn new(param: &HashMap<String, String>, init: Arc<RwLock<Init>>) -> Result<Param, Error> {
         let init_read = RwLock::read(&init).unwrap();
         let price_param = Param::get_price_param(param);
-        let format_str;
-        let format = match price_param.get("format") {
+        let mut format_str;
+        let mut format = match price_param.get("format") {
             Some(format) => {
                 format_str = format.clone();
-                match format as &str {
-                    "xlsx" => Format::XLSX,
-                    "xml" => Format::XML,
-                    "json" => Format::JSON,
-                    "php" => Format::PHP,
-                    _ => return Err(log_read.client_err(2))
+                match Format::from_str(format) {
+                    Some(format) => format,
+                    None => return Err(Error::FormatUnsupported),
                 }
             },
-            None => return Err(log_read.client_err(1)),
+            None => return Err(Error::FormatMissing),
         };
         let volume_str;
         let volume = match price_param.get("full") {
@@ -78,12 +479,12 @@ impl Param {
                     "1" => PriceVolume::Full,
                     "2" => PriceVolume::Short,
                     "3" => PriceVolume::FullUAH,
-                    _ => return Err(log_read.client_err(4))
+                    _ => return Err(Error::FullUnsupported)
                 };
                 volume_str = format.clone();
                 f
             },
-            None => return Err(log_read.client_err(3)),
+            None => return Err(Error::FullMissing),
         };
         let uah = match price_param.get("cur") {
             Some(uah) => match uah as &str {
@@ -111,16 +512,16 @@ impl Param {
         let company_id: u32 = match price_param.get("companyID") {
             Some(company_id) => match company_id.parse() {
                 Ok(company_id) => company_id,
-                Err(_) => return Err(log_read.client_err(6)),
+                Err(_) => return Err(Error::CompanyFormat),
             },
-            None => return Err(log_read.client_err(5)),
+            None => return Err(Error::CompanyMissing),
         };
         let target_id: u32 = match price_param.get("targetID") {
             Some(target_id) => match target_id.parse() {
                 Ok(target_id) => target_id,
-                Err(_) => return Err(log_read.client_err(8)),
+                Err(_) => return Err(Error::TargetFormat),
             },
-            None => return Err(log_read.client_err(7)),
+            None => return Err(Error::TargetMissing),
         };
         let lang_str;
         let lang = match price_param.get("lang") {
@@ -129,24 +530,24 @@ impl Param {
                 match lang as &str {
                     "ua" => Lang::UA,
                     "ru" => Lang::RU,
-                    _ => return Err(log_read.client_err(10))
+                    _ => return Err(Error::LangFormat)
                 }
             },
-            None => return Err(log_read.client_err(9)),
+            None => return Err(Error::LangMissing),
         };
         let time: u32 = match price_param.get("time") {
             Some(time) => match time.parse() {
                 Ok(time) => time,
-                Err(_) => return Err(log_read.client_err(12)),
+                Err(_) => return Err(Error::TimeFormat),
             },
-            None => return Err(log_read.client_err(11)),
+            None => return Err(Error::TimeMissing),
         };
         let user_id: u32 = match price_param.get("userID") {
             Some(user_id) => match user_id.parse() {
                 Ok(user_id) => user_id,
-                Err(_) => return Err(log_read.client_err(14)),
+                Err(_) => return Err(Error::UserFormat),
             },
-            None => return Err(log_read.client_err(13)),
+            None => return Err(Error::UserMissing),
         };
         let pc_vinga_str;
         let pc_vinga = match price_param.get("pcvinga") {
@@ -169,15 +570,41 @@ impl Param {
             Some(_) => true,
             None => false,
         };
+        let sort = match price_param.get("sort") {
+            Some(sort) => match Sort::from_str(sort) {
+                Some(sort) => sort,
+                None => return Err(Error::SortUnsupported),
+            },
+            None => Sort::Category,
+        };
+        let csv = CsvOptions::new(format, &lang, &price_param)?;
+        let page = Page::new(format, &price_param)?;
+        let filter = Filter::new(&price_param)?;
+        let lookup = Lookup::new(format, &price_param)?;
+        if lookup.is_some() && page.is_some() {
+            return Err(Error::LookupUnsupported);
+        }
+        let mut fields: Vec<String> = Vec::new();
+        if let Some(list) = price_param.get("fields") {
+            for name in list.split(',').map(|name| name.trim()).filter(|name| name.len() > 0) {
+                if !init_read.columns.exists(name) {
+                    return Err(Error::FieldsUnsupported);
+                }
+                if !fields.iter().any(|f| f == name) {
+                    fields.push(name.to_owned());
+                }
+            }
+        }
         let token = match price_param.get("token") {
             Some(token) => token.clone(),
-            None => return Err(log_read.client_err(15)),
+            None => return Err(Error::TokenMissing),
         };
-        let mut hasher = Sha512::new();
-        hasher.update(format!("{}{}{}{}{}{}", company_id, target_id, format_str, lang_str, time, init_read.salt).as_bytes());
-        let result = format!("{:#x}", hasher.finalize());
-        if result != token {
-            return Err(log_read.client_err(16))
+        if Param::token(company_id, target_id, &format_str, &lang_str, time as i64, &filter, &init_read.salt) != token {
+            return Err(Error::TokenInvalid)
+        }
+        if page.is_some() {
+            format = Format::NDJSON;
+            format_str = "ndjson".to_owned();
         }
 
         Ok(Param {
@@ -200,12 +627,72 @@ impl Param {
             round,
             ean,
             api,
-
-            // log: Arc::clone(&log),
+            sort,
+            csv,
+            page,
+            filter,
+            lookup,
+            fields,
         })
     }
 
-    fn get_price_param(param: &HashMap<String, String>) -> HashMap<String, String> {
+    // Канонічний рядок запиту без time та token, для історії та прогріву
+    pub fn query(&self) -> String {
+        let mut query = format!("companyID={}&userID={}&targetID={}&format={}&lang={}&full={}&pcvinga={}",
+            self.company_id, self.user_id, self.target_id, self.format_str, self.lang_str, self.volume_str, self.pc_vinga_str);
+        if self.uah {
+            query.push_str("&cur=uah");
+        }
+        if self.nds_orig {
+            query.push_str("&nds=1");
+        }
+        if self.ean {
+            query.push_str("&ean=1");
+        }
+        if self.api {
+            query.push_str("&api=1");
+        }
+        if self.sort != Sort::Category {
+            query.push_str(&format!("&sort={}", self.sort.as_str()));
+        }
+        if let Some(csv) = &self.csv {
+            query.push('&');
+            query.push_str(&csv.query());
+        }
+        if !self.filter.is_empty() {
+            query.push('&');
+            query.push_str(&self.filter.query());
+        }
+        if self.fields.len() > 0 {
+            query.push_str(&format!("&fields={}", self.fields.join(",")));
+        }
+        query
+    }
+
+    // Параметри FastCGI для внутрішнього запиту: до рядка запиту додаються поточний time та token
+    pub fn signed(query: &str, salt: &str) -> HashMap<String, String> {
+        let mut param = HashMap::with_capacity(1);
+        param.insert("QUERY_STRING".to_owned(), query.to_owned());
+        let get = Param::get_price_param(&param);
+        let empty = String::new();
+        let time = Local::now().timestamp();
+        let id = |name| get.get(name).and_then(|id: &String| id.parse().ok()).unwrap_or(0);
+        let filter = Filter::new(&get).unwrap_or_default();
+        let token = Param::token(id("companyID"), id("targetID"), get.get("format").unwrap_or(&empty), get.get("lang").unwrap_or(&empty), time, &filter, salt);
+        param.insert("QUERY_STRING".to_owned(), format!("{}&time={}&token={}", query, time, token));
+        param
+    }
+
+    // Підпис посилання на прайс. Канонічний фільтр підписується, щоб власник посилання не міг розширити вибірку;
+    // без фільтра підпис збігається з посиланнями, виданими до появи фільтрів.
+    // fields, сторінка та вибрані товари не підписуються: вони перевіряються за правами компанії при кожному запиті
+    pub fn token(company_id: u32, target_id: u32, format: &str, lang: &str, time: i64, filter: &Filter, salt: &str) -> String {
+        let mut hasher = Sha512::new();
+        hasher.update(format!("{}{}{}{}{}{}{}", company_id, target_id, format, lang, time, filter.query(), salt).as_bytes());
+        format!("{:#x}", hasher.finalize())
+    }
+
+    pub fn get_price_param(param: &HashMap<String, String>) -> HashMap<String, String> {
         let mut get: HashMap<String, String> = HashMap::with_capacity(16);
         let key = "QUERY_STRING";
         if param.contains_key(key) {
@@ -221,4 +708,103 @@ impl Param {
         }
         get
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(text: &str) -> HashMap<String, String> {
+        text.split('&').filter(|v| v.len() > 0).map(|v| match v.split_once('=') {
+            Some((k, v)) => (k.to_owned(), v.to_owned()),
+            None => (v.to_owned(), String::new()),
+        }).collect()
+    }
+
+    fn page(text: &str) -> Result<Option<Page>, Error> {
+        Page::new(Format::JSON, &query(text))
+    }
+
+    // Параметри з рядка запиту так само, як у FastCGI запиті
+    fn decoded(text: &str) -> HashMap<String, String> {
+        let mut param = HashMap::new();
+        param.insert("QUERY_STRING".to_owned(), text.to_owned());
+        Param::get_price_param(&param)
+    }
+
+    #[test]
+    fn filter_parse() {
+        let f = Filter::new(&decoded("category=5,3,,5&vendor=HP,%20Apple%20,12,7,hp&group=2&class=9,1&stock=1&delivery=3&price_min=12,5&price_max=100")).unwrap();
+        assert_eq!(f.category, vec![3, 5]);
+        assert_eq!(f.vendor, vec!["apple", "hp"]);
+        assert_eq!(f.vendor_id, vec![7, 12]);
+        assert_eq!((f.group.clone(), f.class.clone()), (vec![2], vec![1, 9]));
+        assert_eq!((f.stock, f.delivery, f.price_min, f.price_max), (true, Some(3), Some(12.5), Some(100.0)));
+        assert!(Filter::new(&decoded("")).unwrap().is_empty());
+        for bad in ["category=1,x", "group=-1", "stock=yes", "delivery=1.5", "price_min=-1", "price_max=abc", "price_min=NaN"] {
+            assert_eq!(Filter::new(&decoded(bad)).unwrap_err(), Error::FilterFormat, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn filter_query() {
+        let f = Filter::new(&decoded("price_max=99,9&vendor=12,Western%20Digital&category=7,2&stock=1")).unwrap();
+        let query = f.query();
+        assert_eq!(query, "category=2,7&vendor=western%20digital,12&stock=1&price_max=99.9");
+        // Канонічний рядок розбирається в той самий фільтр
+        assert_eq!(Filter::new(&decoded(&query)).unwrap().query(), query);
+        // Порядок та повтори значень не змінюють ключ кеша
+        let same = Filter::new(&decoded("stock=1&category=2,7,2&vendor=western%20digital,12&price_max=99.90")).unwrap();
+        assert_eq!(same.query(), query);
+    }
+
+    // Перевірка підпису так само, як у Param::new
+    fn signed_valid(query: &str) -> bool {
+        let get = decoded(query);
+        let filter = match Filter::new(&get) {
+            Ok(filter) => filter,
+            Err(_) => return false,
+        };
+        let time: i64 = get["time"].parse().unwrap();
+        Param::token(get["companyID"].parse().unwrap(), get["targetID"].parse().unwrap(), &get["format"], &get["lang"], time, &filter, "salt") == get["token"]
+    }
+
+    #[test]
+    fn token_filter() {
+        let link = Param::signed("companyID=10&userID=2&targetID=3&format=json&lang=ua&full=1&category=5&stock=1", "salt");
+        let link = &link["QUERY_STRING"];
+        assert!(signed_valid(link));
+        // Інший порядок тих самих значень дає той самий канонічний фільтр
+        assert!(signed_valid(&link.replace("category=5&stock=1", "stock=1&category=5,5")));
+        for tampered in ["category=6&stock=1", "category=5", "category=5,6&stock=1", "category=5&stock=1&vendor=hp"] {
+            assert!(!signed_valid(&link.replace("category=5&stock=1", tampered)), "{}", tampered);
+        }
+        // Посилання без фільтра підписані як до появи фільтрів
+        let mut hasher = Sha512::new();
+        hasher.update(format!("{}{}{}{}{}{}", 10, 3, "json", "ua", 1700000000, "salt").as_bytes());
+        assert_eq!(Param::token(10, 3, "json", "ua", 1700000000, &Filter::default(), "salt"), format!("{:#x}", hasher.finalize()));
+    }
+
+    #[test]
+    fn page_limit_offset() {
+        assert!(page("").unwrap().is_none());
+        let p = page("limit=50&offset=100").unwrap().unwrap();
+        assert_eq!((p.limit, p.offset, p.generation), (50, 100, None));
+        let p = page("offset=10").unwrap().unwrap();
+        assert_eq!((p.limit, p.offset), (PAGE_LIMIT, 10));
+        assert_eq!(page("limit=0").unwrap_err(), Error::PageUnsupported);
+        assert_eq!(page(&format!("limit={}", PAGE_MAX + 1)).unwrap_err(), Error::PageUnsupported);
+        assert_eq!(page("offset=-1").unwrap_err(), Error::PageUnsupported);
+        assert_eq!(Page::new(Format::XML, &query("limit=10")).unwrap_err(), Error::PageUnsupported);
+    }
+
+    #[test]
+    fn page_cursor() {
+        let p = page(&format!("limit=20&cursor={}", Page::cursor(7, 40))).unwrap().unwrap();
+        assert_eq!((p.limit, p.offset, p.generation), (20, 40, Some(7)));
+        assert_eq!(page("cursor=7.40&offset=40").unwrap_err(), Error::PageUnsupported);
+        assert_eq!(page("cursor=7").unwrap_err(), Error::PageUnsupported);
+        assert_eq!(page("cursor=7.x").unwrap_err(), Error::PageUnsupported);
+        assert_eq!(page("cursor=").unwrap_err(), Error::PageUnsupported);
+    }
+}
@@ -1,9 +1,9 @@
-use std::{sync::{Mutex, Arc, RwLock}, collections::HashMap, fs::{remove_file, read}, path::Path};
+use std::{sync::{Mutex, Arc, RwLock}, collections::{HashMap, HashSet}, fs::{read, metadata, File}, io::{BufRead, BufReader}, path::Path, time::Instant};
 
-use crate::{worker::Worker, param::{Format, Param, PriceVolume, Lang}, cache::Cache, log::Log, init::Init, data::{Product, LockList, Target, ProductStock, BonusGroup, Country}, db::DB, format_xlsx::FormatXLSX, format_php::FormatPHP, format_xml::FormatXml, format_json::FormatJSON};
+use crate::{worker::Worker, param::{Format, Param, PriceVolume, Lang, Sort, Page}, cache::Cache, log::{Log, LogCtx}, init::Init, data::{Product, LockList, Target, ProductStock, BonusGroup, Country, Categories}, db::DB, format_xml::FormatXml, format_json::FormatJSON, audit::AuditRecord, error::Error, message::LANG_DEFAULT, response::Response, compress::Encoding, flight::Flight, meta::PriceMeta, column::{Columns, Kind, Permission}};
 
-use chrono::{NaiveDateTime, Local, TimeZone, Duration};
-use glob::glob;
+use chrono::{Local, TimeZone, DateTime, Utc};
+use sha2::{Sha256, Digest};
 
 pub enum ValueType<'a> {
     String(&'a str),
@@ -16,12 +16,21 @@ pub struct ItemShow<'a> {
     pub full: bool,
     pub short: bool,
     pub full_uah: bool,
-    pub index: Option<String>,
     pub rozn: bool,
     pub r3: bool,
     pub ean: bool,
     pub name: &'a str,
-    pub get: Box<dyn Fn(&'a PriceItem) -> ValueType>,
+    pub source: &'a str,                // Поле товару, назва колонки може змінюватись в columns.json
+    pub ua: &'a str,                    // Заголовок колонки
+    pub ru: &'a str,
+    pub decimals: usize,                // Знаків після коми для ціни
+    pub get: Box<dyn Fn(&'a PriceItem) -> ValueType<'a>>,
+}
+
+impl<'a> ItemShow<'a> {
+    pub fn title(&self, ua: bool) -> &'a str {
+        if ua { self.ua } else { self.ru }
+    }
 }
 
 pub struct Show<'a> {
@@ -29,95 +38,95 @@ pub struct Show<'a> {
 }
 
 impl<'a> Show<'a> {
-    pub fn new() -> Show<'a> {
+    // Колонки з каталогу columns.json, поля товарів перевірені при запуску
+    pub fn new(columns: &'a Columns) -> Show<'a> {
         let mut show = Show {
-            list: Vec::with_capacity(34)
+            list: Vec::with_capacity(columns.list.len())
         };
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Index(val.category_id) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "CategoryID", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.code) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "Code", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.group) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: false, rozn: false, r3: false, ean: false, name: "Group", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.articul) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Article", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.vendor) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Vendor", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.model) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Model", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.name) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Name", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.description) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: false, rozn: false, r3: false, ean: false, name: "Description", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Money(val.price_usd) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "PriceUSD", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Money(val.price_uah) });
-        show.list.push(ItemShow { local: false, full: false, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "PriceUAH", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Index(val.price_ind) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Price_ind", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.category_name) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "CategoryName", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Money(val.bonus) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "Bonus", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Money(val.recommended_price) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "RecommendedPrice", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.ddp) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: false, rozn: false, r3: false, ean: false, name: "DDP", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Index(val.warranty) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Warranty", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.stock) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "Stock", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.note) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: false, rozn: false, r3: false, ean: false, name: "Note", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.day_delivery) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "DayDelivery", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Index(val.id) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: true, rozn: false, r3: false, ean: false, name: "ProductID", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.url) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: false, rozn: false, r3: false, ean: false, name: "URL", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.uktved) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "UKTVED", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Index(val.group_id) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "GroupID", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Index(val.class_id) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "ClassID", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.class_name) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "ClassName", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.available) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Available", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.country) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Country", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Money(val.retail_price) });
-        show.list.push(ItemShow { local: false, full: false, short: false, full_uah: true, rozn: true, r3: false, ean: false, name: "RetailPrice", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Money(val.internet_price) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: false, rozn: false, r3: true, ean: false, name: "InternetPrice", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::Money(val.cost_delivery) });
-        show.list.push(ItemShow { local: true, full: true, short: true, full_uah: false, rozn: false, r3: false, ean: false, name: "CostDelivery", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.available) });
-        show.list.push(ItemShow { local: false, full: false, short: true, full_uah: false, rozn: false, r3: false, ean: false, name: "Available", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.exclusive) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "Exclusive", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.ean) });
-        show.list.push(ItemShow { local: false, full: false, short: false, full_uah: true, rozn: false, r3: false, ean: true, name: "EAN", get, index: None });
-        let get = Box::new(| val: &'a PriceItem | { ValueType::String(&val.fop) });
-        show.list.push(ItemShow { local: true, full: true, short: false, full_uah: true, rozn: false, r3: false, ean: false, name: "FOP", get, index: None });
+        for col in &columns.list {
+            let (_, get) = PriceItem::source(&col.source).unwrap();
+            show.list.push(ItemShow {
+                local: col.local,
+                full: col.full,
+                short: col.short,
+                full_uah: col.full_uah,
+                rozn: col.permission == Permission::Rozn,
+                r3: col.permission == Permission::R3,
+                ean: col.permission == Permission::Ean,
+                name: &col.name,
+                source: &col.source,
+                ua: &col.ua,
+                ru: &col.ru,
+                decimals: col.decimals,
+                get,
+            });
+        }
         show
     }
+
+    // Індекси колонок прайса за обсягом та додатковими правами компанії
+    pub fn select(&self, volume: &PriceVolume, rozn: bool, r3: bool, ean: bool) -> Vec<usize> {
+        self.list.iter().enumerate().filter(|(_, item)| {
+            let base = match volume {
+                PriceVolume::Local => item.local,
+                PriceVolume::Full => item.full,
+                PriceVolume::Short => item.short,
+                PriceVolume::FullUAH => item.full_uah,
+            };
+            base || (item.rozn && rozn) || (item.r3 && r3) || (item.ean && ean)
+        }).map(|(i, _)| i).collect()
+    }
+
+    // Колонки з параметра fields в заданому порядку. Роздрібна та інтернет ціна лише з відповідними правами компанії
+    pub fn fields(&self, fields: &[String], rozn: bool, r3: bool) -> Result<Vec<usize>, Error> {
+        let mut list = Vec::with_capacity(fields.len());
+        for name in fields {
+            let i = match self.list.iter().position(|item| item.name == name) {
+                Some(i) => i,
+                None => return Err(Error::FieldsUnsupported),
+            };
+            let item = &self.list[i];
+            if (item.rozn && !rozn) || (item.r3 && !r3) {
+                return Err(Error::FieldsForbidden);
+            }
+            list.push(i);
+        }
+        Ok(list)
+    }
+
+    // Індекси колонок прайса: з параметра fields або за обсягом прайса.
+    // Каталог колонок не змінюється після запуску, тому індекси перевіряються один раз на запит
+    pub fn columns(&self, param: &Param, rozn: bool, r3: bool) -> Result<Vec<usize>, Error> {
+        if param.fields.len() > 0 {
+            self.fields(&param.fields, rozn, r3)
+        } else {
+            Ok(self.select(&param.volume, rozn, r3, param.ean))
+        }
+    }
+
+    // Колонки за індексами з columns()
+    pub fn get(&self, columns: &[usize]) -> Vec<&ItemShow<'a>> {
+        columns.iter().map(|&i| &self.list[i]).collect()
+    }
 }
 
 #[derive(Debug)]
 pub struct Price {
     worker: Arc<Mutex<Worker>>,
     items: HashMap<u32, PriceItem>,
+    record: AuditRecord,
+    ctx: LogCtx,                        // Контекст запиту для журналу
+    lang: String,                       // Мова повідомлень про помилки
+    format: Option<Format>,             // Формат відповіді з помилкою
 }
 
 #[derive(Debug)]
 pub struct PriceItem {
     pub id: u32,
-    code: String,
-    stock: String,
-    available: String,
-    day_delivery: String,
+    pub code: String,
+    pub stock: String,
+    pub available: String,
+    pub day_delivery: String,
     // bg: String,
     // bonus_opt: f32,
     bonus: f32,
@@ -130,32 +139,73 @@ pub struct PriceItem {
     // volume: f32,
     // overall: u32,
     cost_delivery: f32,
-    category_id: u32,
+    pub category_id: u32,
     group: String,
-    articul: String,
-    vendor: String,
-    model: String,
-    name: String,
-    description: String,
+    pub articul: String,
+    pub vendor: String,
+    pub model: String,
+    pub name: String,
+    pub description: String,
     category_name: String,
     ddp: String,
-    warranty: u32,
+    pub warranty: u32,
     note: String,
-    url: String,
+    pub url: String,
     uktved: String,
     group_id: u32,
     class_id: u32,
     class_name: String,
     // country_id: u32,
-    country: String,
+    pub country: String,
     exclusive: String,
     lock: bool,
-    ean: String,
+    pub ean: String,
     fop: String,
-    price_uah: f32,
+    pub price_uah: f32,
 }
 
 impl PriceItem {
+    // Поле товару для колонки прайса за назвою з columns.json
+    pub fn source<'a>(name: &str) -> Option<(Kind, Box<dyn Fn(&'a PriceItem) -> ValueType<'a>>)> {
+        let res: (Kind, Box<dyn Fn(&'a PriceItem) -> ValueType<'a>>) = match name {
+            "category_id" => (Kind::Index, Box::new(| val: &'a PriceItem | { ValueType::Index(val.category_id) })),
+            "code" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.code) })),
+            "group" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.group) })),
+            "articul" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.articul) })),
+            "vendor" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.vendor) })),
+            "model" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.model) })),
+            "name" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.name) })),
+            "description" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.description) })),
+            "price_usd" => (Kind::Money, Box::new(| val: &'a PriceItem | { ValueType::Money(val.price_usd) })),
+            "price_uah" => (Kind::Money, Box::new(| val: &'a PriceItem | { ValueType::Money(val.price_uah) })),
+            "price_ind" => (Kind::Index, Box::new(| val: &'a PriceItem | { ValueType::Index(val.price_ind) })),
+            "category_name" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.category_name) })),
+            "bonus" => (Kind::Money, Box::new(| val: &'a PriceItem | { ValueType::Money(val.bonus) })),
+            "recommended_price" => (Kind::Money, Box::new(| val: &'a PriceItem | { ValueType::Money(val.recommended_price) })),
+            "ddp" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.ddp) })),
+            "warranty" => (Kind::Index, Box::new(| val: &'a PriceItem | { ValueType::Index(val.warranty) })),
+            "stock" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.stock) })),
+            "note" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.note) })),
+            "day_delivery" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.day_delivery) })),
+            "id" => (Kind::Index, Box::new(| val: &'a PriceItem | { ValueType::Index(val.id) })),
+            "url" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.url) })),
+            "uktved" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.uktved) })),
+            "group_id" => (Kind::Index, Box::new(| val: &'a PriceItem | { ValueType::Index(val.group_id) })),
+            "class_id" => (Kind::Index, Box::new(| val: &'a PriceItem | { ValueType::Index(val.class_id) })),
+            "class_name" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.class_name) })),
+            "available" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.available) })),
+            "country" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.country) })),
+            "retail_price" => (Kind::Money, Box::new(| val: &'a PriceItem | { ValueType::Money(val.retail_price) })),
+            "internet_price" => (Kind::Money, Box::new(| val: &'a PriceItem | { ValueType::Money(val.internet_price) })),
+            "cost_delivery" => (Kind::Money, Box::new(| val: &'a PriceItem | { ValueType::Money(val.cost_delivery) })),
+            "exclusive" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.exclusive) })),
+            "ean" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.ean) })),
+            "fop" => (Kind::String, Box::new(| val: &'a PriceItem | { ValueType::String(&val.fop) })),
+            _ => return None,
+        };
+        Some(res)
+    }
+
     pub fn new(product_id: u32, param: &Param, p: Product, l: &LockList, s: &ProductStock, b: &BonusGroup, t: &Target, h: &str, c: &HashMap<u32, Country>, target_id: u32) -> Option<PriceItem> {
         let fop: &str = if p.seller.len() > 0 { "1" } else { "0" };
 
@@ -366,168 +416,558 @@ impl Price {
         Price {
             worker,
             items: HashMap::with_capacity(cap),
+            record: AuditRecord::default(),
+            ctx: LogCtx::default(),
+            lang: LANG_DEFAULT.to_owned(),
+            format: None,
         }
     }
     
-    pub fn calc(&mut self, param: &HashMap<String, String>) -> Vec<u8> {
-        if cfg!(debug_assertions) {
-            println!("{} Start price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
+    pub fn calc(&mut self, param: &HashMap<String, String>) -> Response {
+        self.ctx = LogCtx::new();
+        let log;
+        {
+            let w = Mutex::lock(&self.worker).unwrap();
+            log = Arc::clone(&w.log);
+        }
+        let log = RwLock::read(&log).unwrap();
+        let span = log.span("price", Some(&self.ctx));
+        let start = Instant::now();
+        self.record.time = Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+        self.record.ip = match param.get("REMOTE_ADDR") {
+            Some(ip) => ip.clone(),
+            None => "".to_owned(),
+        };
+        let answer = self.answer(param);
+        if self.record.status > 0 {
+            self.record.bytes = answer.body.len();
+            self.record.duration = start.elapsed().as_millis() as u64;
+            let audit;
+            {
+                let w = Mutex::lock(&self.worker).unwrap();
+                audit = w.audit.clone();
+            }
+            if let Err(_) = audit.send(std::mem::take(&mut self.record)) { }
         }
+        drop(span);
+        answer
+    }
+
+    fn answer(&mut self, param: &HashMap<String, String>) -> Response {
         let log;
         let init;
         let cache;
+        let limit;
+        let flight;
+        let stop;
         {
             let w = Mutex::lock(&self.worker).unwrap();
-            if w.stop {
-                if cfg!(debug_assertions) {
-                    println!("{} Finish price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
-                }
-                return Vec::new();
-            }
+            stop = w.stop;
+            flight = Arc::clone(&w.flight);
             log = Arc::clone(&w.log);
             init = Arc::clone(&w.init);
             cache = Arc::clone(&w.cache);
+            limit = Arc::clone(&w.limit);
         }
-        let param = match Param::new(param, Arc::clone(&init), Arc::clone(&log)) {
+        {
+            let i = RwLock::read(&init).unwrap();
+            let query = Param::get_price_param(param);
+            self.lang = i.messages.lang(query.get("lang"), param.get("HTTP_ACCEPT_LANGUAGE"));
+            self.format = query.get("format").and_then(|f| Format::from_str(f));
+        }
+        if stop {
+            return self.error(Error::ServerStopped, Arc::clone(&log));
+        }
+        let if_none_match = param.get("HTTP_IF_NONE_MATCH").cloned();
+        let if_modified_since = param.get("HTTP_IF_MODIFIED_SINCE").cloned();
+        let accept_encoding = param.get("HTTP_ACCEPT_ENCODING").cloned();
+        let param = match Param::new(param, Arc::clone(&init)) {
             Ok(param) => param,
-            Err(err) => {
-                let text = err.as_bytes();
-                let mut answer = format!("HTTP/1.1 401 Unauthorized\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n", text.len()).as_bytes().to_vec();
-                answer.extend_from_slice(&text[..]);
-                if cfg!(debug_assertions) {
-                    println!("{} Finish price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
-                }
-                return answer;
-            },
+            Err(err) => return self.error(err, Arc::clone(&log)),
         };
+        self.ctx.company = param.company_id;
+        self.set_record(&param);
 
-        let (corp, rozn, r3, profile_id) = match self.check_auth(&param, Arc::clone(&cache), Arc::clone(&log)) {
+        let (corp, rozn, r3, profile_id) = match self.check_auth(&param, Arc::clone(&cache)) {
             Ok((corp, rozn, r3, profile_id)) => (corp, rozn, r3, profile_id),
-            Err(err) => {
-                let text = err.as_bytes();
-                let mut answer = format!("HTTP/1.1 401 Unauthorized\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n", text.len()).as_bytes().to_vec();
-                answer.extend_from_slice(&text[..]);
-                if cfg!(debug_assertions) {
-                    println!("{} Finish price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
-                }
-                return answer;
-            },
+            Err(err) => return self.error(err, Arc::clone(&log)),
         };
 
-        let file = match self.get_file_name(&param, Arc::clone(&init), Arc::clone(&log)) {
-            Ok(file) => file,
-            Err(err) => {
-                let text = err.as_bytes();
-                let mut answer = format!("HTTP/1.1 401 Unauthorized\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n", text.len()).as_bytes().to_vec();
-                answer.extend_from_slice(&text[..]);
-                if cfg!(debug_assertions) {
-                    println!("{} Finish price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
-                }
+        let rate;
+        {
+            let mut l = Mutex::lock(&limit).unwrap();
+            rate = l.check_rate(param.company_id, param.user_id);
+        }
+        if let Err(retry) = rate {
+            return self.too_many(Error::RateLimit, retry, Arc::clone(&log));
+        }
+        let columns;
+        {
+            let i = RwLock::read(&init).unwrap();
+            columns = Show::new(&i.columns).columns(&param, rozn, r3);
+        }
+        let columns = match columns {
+            Ok(columns) => columns,
+            Err(err) => return self.error(err, Arc::clone(&log)),
+        };
+
+        // Вибрані товари формуються щоразу заново, кеш та квота генерацій не використовуються
+        if param.lookup.is_some() {
+            let text = match self.get_price(&param, &columns, None, corp, rozn, r3, profile_id) {
+                Ok(text) => text,
+                Err(err) => return self.error(err, Arc::clone(&log)),
+            };
+            self.record.status = 200;
+            let mut answer = Response::new(200);
+            answer.header("Content-Type", param.format.content_type());
+            answer.header("Cache-Control", "no-store");
+            answer.body(text);
+            return answer;
+        }
+
+        let generation;
+        {
+            let c = Mutex::lock(&cache).unwrap();
+            generation = c.generation;
+        }
+        if param.page.map_or(false, |page| page.stale(generation)) {
+            return self.error(Error::CursorExpired, Arc::clone(&log));
+        }
+        let key = Price::cache_key(&param, generation);
+
+        // Однаковий прайс генерує лише один потік, інші чекають і віддають готовий файл.
+        // Недійсний файл видаляє лише власник генерації
+        let mut _guard = None;
+        let file = loop {
+            let (file, valid) = match self.get_file_name(&param, &key, _guard.is_some(), Arc::clone(&init)) {
+                Ok(res) => res,
+                Err(err) => return self.error(err, Arc::clone(&log)),
+            };
+            if valid || _guard.is_some() {
+                break file;
+            }
+            _guard = Flight::start(Arc::clone(&flight), &key);
+        };
+
+        // xlsx та cml вже є zip архівами, сторінка JSON вирізається з нестиснутого файлу
+        let encoding = if param.format.archive() || param.page.is_some() {
+            Encoding::Identity
+        } else {
+            Encoding::negotiate(accept_encoding.as_ref())
+        };
+
+        let generate = !Path::new(&file).exists();
+        if !generate && param.page.is_none() {
+            if let Some(answer) = self.not_modified(&file, encoding, if_none_match.as_ref(), if_modified_since.as_ref()) {
                 return answer;
+            }
+        }
+        if generate {
+            let quota;
+            {
+                let mut l = Mutex::lock(&limit).unwrap();
+                quota = l.check_quota(param.company_id, param.user_id);
+            }
+            if let Err(retry) = quota {
+                return self.too_many(Error::Quota, retry, Arc::clone(&log));
+            }
+        }
+
+        // Стиснений варіант вже збережений в кеші, оригінал не читається
+        let mut encoded = None;
+        if !generate && encoding != Encoding::Identity {
+            if let Ok(data) = read(encoding.file(&file)) {
+                encoded = Some(data);
+            }
+        }
+        let mut used = encoding;
+        let meta = if generate { None } else { PriceMeta::read(&file) };
+        if meta.is_some() {
+            PriceMeta::touch(&file);
+        }
+        // Сторінка з готового файлу читається рядками до кінця сторінки, весь NDJSON в пам'ять не завантажується
+        if let (Some(page), false) = (param.page, generate) {
+            let total = meta.as_ref().and_then(|meta| meta.rows);
+            let answer = match File::open(&file) {
+                Ok(f) => Price::page(BufReader::new(f), total, page, generation),
+                Err(_) => None,
+            };
+            return match answer {
+                Some(answer) => {
+                    self.done(&param, true);
+                    answer
+                },
+                None => self.error(Error::PriceRead, Arc::clone(&log)),
+            };
+        }
+        let (text, meta) = match encoded {
+            Some(data) => (data, meta),
+            None => {
+                let (text, meta) = if generate {
+                    let mut meta = Price::new_meta(&param, &key, generation, Arc::clone(&init));
+                    let text = match self.get_price(&param, &columns, Some((&file, &mut meta)), corp, rozn, r3, profile_id) {
+                        Ok(text) => text,
+                        Err(err) => return self.error(err, Arc::clone(&log)),
+                    };
+                    {
+                        let mut l = Mutex::lock(&limit).unwrap();
+                        l.add_generation(param.company_id, param.user_id);
+                    }
+                    (text, Some(meta))
+                } else {
+                    match read(&file) {
+                        Ok(text) => (text, meta),
+                        Err(_) => return self.error(Error::PriceRead, Arc::clone(&log)),
+                    }
+                };
+                let text = match encoding {
+                    Encoding::Identity => text,
+                    _ => match encoding.cached(&file, &text, meta.as_ref().map(|meta| &meta.etag[..])) {
+                        Some(data) => data,
+                        None => {
+                            used = Encoding::Identity;
+                            text
+                        },
+                    },
+                };
+                (text, meta)
             },
         };
+        if let Some(page) = param.page {
+            let total = meta.as_ref().and_then(|meta| meta.rows);
+            return match Price::page(&text[..], total, page, generation) {
+                Some(answer) => {
+                    self.done(&param, !generate);
+                    answer
+                },
+                None => self.error(Error::PriceRead, Arc::clone(&log)),
+            };
+        }
+        self.done(&param, !generate);
 
-        let mut answer: Vec<String> = Vec::with_capacity(16);
-        let text = match self.get_price(&param, &file, corp, rozn, r3, profile_id) {
-            Ok(text) => text,
-            Err(err) => {
-                let text = err.as_bytes();
-                let mut answer = format!("HTTP/1.1 401 Unauthorized\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n", text.len()).as_bytes().to_vec();
-                answer.extend_from_slice(&text[..]);
-                if cfg!(debug_assertions) {
-                    println!("{} Finish price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
+        let mut answer = Response::new(200);
+        answer.header("Content-Type", param.format.content_type());
+        let created = match &meta {
+            Some(meta) => Local.timestamp_opt(meta.created, 0).single().unwrap_or(Local::now()),
+            None => Local::now(),
+        };
+        let file_name = format!("price_{}_{}_{}_{}_{}_{}_{}.{}", param.company_id, param.user_id, param.target_id, param.lang_str, param.volume_str, param.pc_vinga_str, created.format("%Y%m%d_%H%M%S"), param.format_str);
+        answer.header("Content-Disposition", &format!("attachment; filename=\"{}\"", file_name));
+        if used != Encoding::Identity {
+            answer.header("Content-Encoding", used.as_str());
+        }
+        if !param.format.archive() {
+            answer.header("Vary", "Accept-Encoding");
+        }
+        if let Some(meta) = meta {
+            answer.header("ETag", &Price::encoded_etag(&meta.etag, used));
+        }
+        if let Some(modified) = Price::last_modified(&file) {
+            answer.header("Last-Modified", &Price::http_date(modified));
+        }
+        answer.body(text);
+        answer
+    }
+
+    // Успішна відповідь: запис журналу та історія запитів для прогріву
+    fn done(&mut self, param: &Param, hit: bool) {
+        self.record.hit = hit;
+        self.record.status = 200;
+        let w = Mutex::lock(&self.worker).unwrap();
+        let mut h = Mutex::lock(&w.history).unwrap();
+        h.add(param.query());
+    }
+
+    // Сторінка JSON API з рядків NDJSON, next - курсор наступної сторінки.
+    // Рядки читаються по одному і не зберігаються, читання закінчується на кінці сторінки.
+    // total - кількість рядків з метаданих, без неї рядки рахуються до кінця файлу
+    fn page<R: BufRead>(mut reader: R, total: Option<usize>, page: Page, generation: u64) -> Option<Response> {
+        let end = page.offset.saturating_add(page.limit);
+        let mut items = Vec::with_capacity(page.limit * 512);
+        let mut line = Vec::with_capacity(4096);
+        let mut count = 0;
+        while total.map_or(true, |total| count < total.min(end)) {
+            line.clear();
+            if reader.read_until(b'\n', &mut line).ok()? == 0 {
+                break;
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if line.len() == 0 {
+                continue;
+            }
+            if count >= page.offset && count < end {
+                if items.len() > 0 {
+                    items.push(b',');
                 }
-                return answer;
+                items.extend_from_slice(&line);
+            }
+            count += 1;
+        }
+        let total = total.unwrap_or(count);
+        let start = page.offset.min(total);
+        let end = end.min(total);
+        let next = if end < total { format!("\"{}\"", Page::cursor(generation, end)) } else { "null".to_owned() };
+        let mut body = Vec::with_capacity(items.len() + 256);
+        body.extend_from_slice(format!("{{\"generation\":{},\"total\":{},\"offset\":{},\"limit\":{},\"next\":{},\"items\":[", generation, total, start, page.limit, next).as_bytes());
+        body.extend_from_slice(&items);
+        body.extend_from_slice(b"]}");
+        let mut answer = Response::new(200);
+        answer.header("Content-Type", "application/json");
+        answer.body(body);
+        Some(answer)
+    }
+
+    // Фонова генерація прайса в кеш без відповіді клієнту, лімити та квоти не враховуються.
+    // Ok(false) - прайс вже є в кеші або генерується іншим потоком
+    pub fn warm(&mut self, query: &str) -> Result<bool, Error> {
+        self.ctx = LogCtx::new();
+        let init;
+        let cache;
+        let flight;
+        {
+            let w = Mutex::lock(&self.worker).unwrap();
+            init = Arc::clone(&w.init);
+            cache = Arc::clone(&w.cache);
+            flight = Arc::clone(&w.flight);
+        }
+        let raw = {
+            let i = RwLock::read(&init).unwrap();
+            Param::signed(query, &i.salt)
+        };
+        let param = Param::new(&raw, Arc::clone(&init))?;
+        self.ctx.company = param.company_id;
+        let (corp, rozn, r3, profile_id) = self.check_auth(&param, Arc::clone(&cache))?;
+        let columns;
+        {
+            let i = RwLock::read(&init).unwrap();
+            columns = Show::new(&i.columns).columns(&param, rozn, r3)?;
+        }
+        let generation;
+        {
+            let c = Mutex::lock(&cache).unwrap();
+            generation = c.generation;
+        }
+        let key = Price::cache_key(&param, generation);
+        if self.get_file_name(&param, &key, false, Arc::clone(&init))?.1 {
+            return Ok(false);
+        }
+        let _guard = match Flight::start(flight, &key) {
+            Some(guard) => guard,
+            None => return Ok(false),
+        };
+        let (file, valid) = self.get_file_name(&param, &key, true, Arc::clone(&init))?;
+        if valid {
+            return Ok(false);
+        }
+        let mut meta = Price::new_meta(&param, &key, generation, init);
+        self.get_price(&param, &columns, Some((&file, &mut meta)), corp, rozn, r3, profile_id)?;
+        Ok(true)
+    }
+
+    // Метадані нового файлу кеша, etag заповнюється під час запису файлу
+    fn new_meta(param: &Param, key: &str, generation: u64, init: Arc<RwLock<Init>>) -> PriceMeta {
+        let ttl;
+        {
+            let i = RwLock::read(&init).unwrap();
+            ttl = i.price_ttl(&param.format_str, param.volume.as_str());
+        }
+        PriceMeta {
+            key: key.to_owned(),
+            created: Local::now().timestamp(),
+            generation,
+            etag: String::new(),
+            ttl,
+            rows: None,
+        }
+    }
+
+    // Канонічний ключ кеша: всі параметри, що впливають на вміст прайса, та покоління даних Cache
+    fn cache_key(param: &Param, generation: u64) -> String {
+        format!("company={};user={};target={};lang={};full={};pcvinga={};format={};cur={};nds={};ean={};api={};sort={};csv={};filter={};fields={};generation={}",
+            param.company_id, param.user_id, param.target_id, param.lang_str, param.volume_str, param.pc_vinga_str, param.format_str,
+            if param.uah { "uah" } else { "usd" }, param.nds_orig as u8, param.ean as u8, param.api as u8, param.sort.as_str(), param.csv.map_or(String::new(), |csv| csv.query()), param.filter.query(), param.fields.join(","), generation)
+    }
+
+    // Відповідь 304, якщо у клієнта актуальна копія прайса (If-None-Match має перевагу над If-Modified-Since)
+    fn not_modified(&mut self, file: &str, encoding: Encoding, if_none_match: Option<&String>, if_modified_since: Option<&String>) -> Option<Response> {
+        let etag = PriceMeta::read(file).map(|meta| Price::encoded_etag(&meta.etag, encoding));
+        let modified = Price::last_modified(file);
+        let fresh = match if_none_match {
+            Some(tags) => match &etag {
+                Some(etag) => tags.split(',').any(|t| {
+                    let t = t.trim();
+                    t == "*" || t.trim_start_matches("W/") == etag
+                }),
+                None => false,
+            },
+            None => match (if_modified_since, modified) {
+                (Some(since), Some(modified)) => match DateTime::parse_from_rfc2822(since) {
+                    Ok(since) => modified.timestamp() <= since.timestamp(),
+                    Err(_) => false,
+                },
+                _ => false,
             },
         };
+        if !fresh {
+            return None;
+        }
+        self.record.hit = true;
+        self.record.status = 304;
+        let mut answer = Response::new(304);
+        if !self.format.map_or(false, |f| f.archive()) {
+            answer.header("Vary", "Accept-Encoding");
+        }
+        if let Some(etag) = etag {
+            answer.header("ETag", &etag);
+        }
+        if let Some(modified) = modified {
+            answer.header("Last-Modified", &Price::http_date(modified));
+        }
+        Some(answer)
+    }
+
+    // Стиснений варіант має власний ETag: "hash-gz"
+    fn encoded_etag(etag: &str, encoding: Encoding) -> String {
+        match encoding {
+            Encoding::Identity => etag.to_owned(),
+            _ => format!("{}-{}\"", etag.trim_end_matches('"'), encoding.ext()),
+        }
+    }
 
-        answer.push("HTTP/1.1 200 OK\r\n".to_owned());
-        match param.format {
-            Format::XLSX => {
-                answer.push("Content-Type: application/vnd.openxmlformats-officedocument.spreadsheetml.sheet\r\n".to_owned());
+    fn last_modified(file: &str) -> Option<DateTime<Utc>> {
+        match metadata(file) {
+            Ok(m) => match m.modified() {
+                Ok(modified) => Some(DateTime::<Utc>::from(modified)),
+                Err(_) => None,
             },
-            Format::XML => {
-                answer.push("Content-Type: application/xml\r\n".to_owned());
+            Err(_) => None,
+        }
+    }
+
+    fn http_date(dt: DateTime<Utc>) -> String {
+        dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+    }
+
+    // Заповнити запис журналу завантажень параметрами запиту
+    fn set_record(&mut self, param: &Param) {
+        self.record.company_id = param.company_id;
+        self.record.user_id = param.user_id;
+        self.record.target_id = param.target_id;
+        // Сторінка JSON API генерується з NDJSON, в журналі залишається запитаний формат
+        self.record.format = if param.page.is_some() { "json".to_owned() } else { param.format_str.clone() };
+        self.record.volume = param.volume_str.clone();
+        self.record.lang = param.lang_str.clone();
+        let mut flags: Vec<&str> = Vec::with_capacity(5);
+        if param.uah {
+            flags.push("cur=uah");
+        }
+        if param.nds_orig {
+            flags.push("nds=1");
+        }
+        if param.ean {
+            flags.push("ean=1");
+        }
+        if param.api {
+            flags.push("api=1");
+        }
+        if param.pc_vinga {
+            flags.push("pcvinga=1");
+        }
+        if param.page.is_some() {
+            flags.push("page");
+        }
+        if !param.filter.is_empty() {
+            flags.push("filter");
+        }
+        if param.lookup.is_some() {
+            flags.push("lookup");
+        }
+        if param.fields.len() > 0 {
+            flags.push("fields");
+        }
+        let sort = format!("sort={}", param.sort.as_str());
+        if param.sort != Sort::Category {
+            flags.push(&sort);
+        }
+        self.record.flags = flags.join(",");
+    }
+
+    fn error(&mut self, err: Error, log: Arc<RwLock<Log>>) -> Response {
+        self.error_answer(err, None, log)
+    }
+
+    fn too_many(&mut self, err: Error, retry: u64, log: Arc<RwLock<Log>>) -> Response {
+        self.error_answer(err, Some(retry), log)
+    }
+
+    // Відповідь з помилкою у форматі запиту клієнта (xlsx - html сторінка)
+    fn error_answer(&mut self, err: Error, retry: Option<u64>, log: Arc<RwLock<Log>>) -> Response {
+        let status = err.status();
+        self.record.status = status;
+        self.record.code = err.code();
+        {
+            let log = RwLock::read(&log).unwrap();
+            log.debug(&format!("client error {}", err.code()), Some(&self.ctx));
+        }
+        let init;
+        {
+            let w = Mutex::lock(&self.worker).unwrap();
+            init = Arc::clone(&w.init);
+        }
+        let init = RwLock::read(&init).unwrap();
+        let (content_type, text) = match self.format {
+            Some(Format::JSON) => {
+                let message = FormatJSON::escape_json(&init.messages.text(&self.lang, err));
+                ("application/json", format!("{{\"error\":{{\"code\":{},\"message\":\"{}\"}}}}", err.code(), message))
             },
-            Format::JSON => {
-                answer.push("Content-Type: application/json\r\n".to_owned());
+            Some(Format::XML) | Some(Format::YML) | Some(Format::GMC) => {
+                let message = FormatXml::escape_xml(&init.messages.text(&self.lang, err));
+                ("application/xml", format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<error><code>{}</code><message>{}</message></error>", err.code(), message))
             },
-            Format::PHP => {
-                answer.push("Content-Type: application/vnd.php.serialized\r\n".to_owned());
+            Some(Format::PHP) => {
+                let message = init.messages.text(&self.lang, err);
+                ("application/vnd.php.serialized", format!("a:1:{{s:5:\"error\";a:2:{{s:4:\"code\";i:{};s:7:\"message\";s:{}:\"{}\";}}}}", err.code(), message.len(), message))
             },
+            _ => ("text/html; charset=utf-8", format!("<!DOCTYPE HTML><html><head><title>PriceList</title><meta charset=\"utf-8\"/></head><body>{}</body></html>", init.messages.get(&self.lang, err))),
         };
-        let file = Path::new(&file).file_name().unwrap().to_str().unwrap().to_owned();
-        answer.push(format!("Content-Disposition: attachment; filename=\"{}\"\r\n", file));
-        answer.push(format!("Content-Length: {}\r\n\r\n", text.len()));
-    
-        let mut answer = answer.join("").into_bytes();
-        answer.extend_from_slice(&text[..]);
-        if cfg!(debug_assertions) {
-            println!("{} Finish price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string())
+        let mut answer = Response::new(status);
+        answer.header("Content-Type", content_type);
+        if let Some(retry) = retry {
+            answer.header("Retry-After", &retry.to_string());
+        }
+        if status >= 500 {
+            answer.stderr(&format!("request {} company {}: error {} {}", self.ctx.request, self.ctx.company, err.code(), err.message()));
         }
+        answer.body(text.into_bytes());
         answer
     }
 
-    fn get_file_name(&self, param: &Param, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Result<String, String> {
-        let log = RwLock::read(&log).unwrap();
+    // Файл кеша за ключем та ознака, що він існує і дійсний. Покоління Cache входить до ключа.
+    // Метадані записуються раніше за файл, тому файл без метаданих недійсний.
+    // Недійсний файл видаляється лише власником генерації (owner), щоб не видалити файл, який зараз публікується
+    fn get_file_name(&self, param: &Param, key: &str, owner: bool, init: Arc<RwLock<Init>>) -> Result<(String, bool), Error> {
         let init = RwLock::read(&init).unwrap();
-        
-        let path = format!("{}/cache/price_{}_{}_{}_{}_{}_{}_*.{}", init.dir, param.company_id, param.user_id, param.target_id, param.lang_str, param.volume_str, param.pc_vinga_str, param.format_str);
-        let fls = match glob(&path) {
-            Ok(fls) => fls,
-            Err(_) => return Err(log.client_err(20)),
-        };
-        let mut log_cache = false;
-        let mut filename: Option<String> = None;
-        let now = Local::now();
-        for entry in fls {
-            if let Ok(fl) = entry {
-                if log_cache {
-                    if let Err(_) = remove_file(fl) {
-                        return Err(log.client_err(21));
-                    }
-                } else {
-                    let file = fl.display().to_string();
-                    let parts: Vec<&str> = file[init.dir.len()..].splitn(8, "_").collect();
-                    if parts.len() == 8 {
-                        let dt = &parts[7][..15];
-                        match NaiveDateTime::parse_from_str(dt, "%Y%m%d_%H%M%S") {
-                            Ok(tm) => {
-                                let tm = Local.from_local_datetime(&tm).unwrap() + Duration::minutes(30);
-                                if tm > now {
-                                    filename = Some(file);
-                                    log_cache = true;
-                                } else {
-                                    if let Err(_) = remove_file(fl) {
-                                        return Err(log.client_err(21));
-                                    }
-                                }
-                            },
-                            Err(_) => {
-                                if let Err(_) = remove_file(fl) {
-                                    return Err(log.client_err(21));
-                                }
-                            },
-                        };
-                    } else {
-                        if let Err(_) = remove_file(fl) {
-                            return Err(log.client_err(21));
-                        }
-                    }
-                }
-            }
+
+        let mut hasher = Sha256::new();
+        hasher.update(key.as_bytes());
+        let hash = format!("{:x}", hasher.finalize());
+        let file = format!("{}/cache/price_{}.{}", init.dir, &hash[..32], param.format_str);
+        if !Path::new(&file).exists() {
+            return Ok((file, false));
         }
-        let file;
-        if let None = filename {
-            file = format!("{}/cache/price_{}_{}_{}_{}_{}_{}_{}.{}", init.dir, param.company_id, param.user_id, param.target_id, param.lang_str, param.volume_str, param.pc_vinga_str, now.format("%Y%m%d_%H%M%S").to_string(), param.format_str);
-         } else {
-            file = filename.unwrap();
+        let valid = match PriceMeta::read(&file) {
+            Some(meta) => meta.key == key && !meta.expired(),
+            None => false,
+        };
+        if !valid && owner && !PriceMeta::remove(&file) {
+            return Err(Error::CacheExpired);
         }
-        Ok(file)
+        Ok((file, valid))
     }
 
-    fn check_auth(&self, param: &Param, cache: Arc<Mutex<Cache>>, log: Arc<RwLock<Log>>) -> Result<(bool, bool, bool, u32), String> {
-        let log = RwLock::read(&log).unwrap();
+    fn check_auth(&self, param: &Param, cache: Arc<Mutex<Cache>>) -> Result<(bool, bool, bool, u32), Error> {
         let auth;
         {
             let c = Mutex::lock(&cache).unwrap();
@@ -543,16 +983,16 @@ impl Price {
                 Some(company) => match company.users.get(&param.user_id) {
                     Some(user) => {
                         if user.profiles_id == 0 {
-                            return Err(log.client_err(19));
+                            return Err(Error::ProfileNotSet);
                         }
                         profile_id = user.profiles_id;
                         corp = user.corp;
                         rozn = user.rozn;
                         r3 = user.r3;
                     },
-                    None => return Err(log.client_err(18)),
+                    None => return Err(Error::UserNotFound),
                 },
-                None => return Err(log.client_err(17)),
+                None => return Err(Error::CompanyNotFound),
             };
         }
         if param.api {
@@ -563,7 +1003,9 @@ impl Price {
         Ok((corp, rozn, r3, profile_id))
     }
 
-    fn get_price(&mut self, param: &Param, file: &str, corp: bool, rozn: bool, r3: bool, profile_id: u32) -> Result<Vec<u8>, String> {
+    // columns - індекси колонок, перевірені Show::columns.
+    // file - новий файл кеша та його метадані, None - прайс вибраних товарів формується в пам'яті
+    fn get_price(&mut self, param: &Param, columns: &[usize], file: Option<(&str, &mut PriceMeta)>, corp: bool, rozn: bool, r3: bool, profile_id: u32) -> Result<Vec<u8>, Error> {
         let log;
         let init;
         {
@@ -573,25 +1015,12 @@ impl Price {
         }
         let log_clone = Arc::clone(&log);
         let init_clone = Arc::clone(&init);
-        let log = RwLock::read(&log).unwrap();
         let init = RwLock::read(&init).unwrap();
 
-        let path = Path::new(file);
-        if path.exists() {
-            match read(path) {
-                Ok(res) => return Ok(res),
-                Err(_) => {
-                    if let Err(_) = remove_file(file) {
-                        return Err(log.client_err(22));
-                    }
-                },
-            };
-        }
-
         let mut target_id = param.target_id;
         
         if target_id == 0 {
-            return Err(log.client_err(23));
+            return Err(Error::TargetZero);
         }
         if param.volume == PriceVolume::FullUAH {
           target_id = 29;
@@ -601,7 +1030,7 @@ impl Price {
         {
             let w = Mutex::lock(&self.worker).unwrap();
             if w.stop {
-                return Err(log.client_err(22));
+                return Err(Error::CacheRemove);
             }
             cache = Arc::clone(&w.cache);
         }
@@ -646,10 +1075,19 @@ impl Price {
             let k = Mutex::lock(&akurs).unwrap();
             kurs = k.clone();
         }
-        let codes;
+        let codes: Vec<u32>;
         {
             let p = Mutex::lock(&prods).unwrap();
-            codes = p.code.clone();
+            codes = match &param.lookup {
+                Some(lookup) => {
+                    let mut list = lookup.ids.clone();
+                    list.extend(lookup.codes.iter().filter_map(|code| p.get_product_id(code.clone())));
+                    list.sort();
+                    list.dedup();
+                    list
+                },
+                None => p.code.values().cloned().collect(),
+            };
         }
         let lock;
         {
@@ -664,7 +1102,7 @@ impl Price {
             let t = Mutex::lock(&targets).unwrap();
             target = match t.target.get(&target_id) {
                 Some(t) => t.clone(),
-                None => return Err(log.client_err(25)),
+                None => return Err(Error::TargetUnknown),
             };
         }
         let stock;
@@ -672,7 +1110,7 @@ impl Price {
             let s = Mutex::lock(&store).unwrap();
             stock = match s.stock.get(&target.stock_id) {
                 Some(s) => s.clone(),
-                None => return Err(log.client_err(26)),
+                None => return Err(Error::TargetNoStore),
             };
         }
         let bg;
@@ -689,18 +1127,32 @@ impl Price {
             country = w.countries.clone();
         }
         let hostname = if corp { "corp.brain.com.ua" } else { "opt.brain.com.ua" };
+        let category;
+        {
+            let c = Mutex::lock(&cache).unwrap();
+            category = Arc::clone(&c.category);
+        }
+        let subtree = if param.filter.category.len() > 0 {
+            let c = Mutex::lock(&category).unwrap();
+            c.subtree(&param.filter.category)
+        } else {
+            HashSet::new()
+        };
 
         let mut ids: Vec<String> = Vec::with_capacity(init.product_capacity);
-        for (_, product_id) in codes {
+        for product_id in codes {
             let p;
             {
                 let pr = Mutex::lock(&prods).unwrap();
                 p = match pr.product.get(&product_id) {
-                    Some(p) => p.clone(),
-                    None => continue,
+                    Some(p) if param.filter.product(p, &subtree) => p.clone(),
+                    _ => continue,
                 };
             }
             if let Some(p) = PriceItem::new(product_id, param, p, &lock, &stock, &bg, &target, hostname, &country, target_id) {
+                if !param.filter.supply(&p) {
+                    continue;
+                }
                 self.items.insert(product_id, p);
                 ids.push(product_id.to_string());
             }
@@ -734,12 +1186,17 @@ impl Price {
                 LEFT JOIN discount_value vd ON vd.DiscountID=cd.DiscountID AND p.ProductGroupID=vd.ProductGroupID
             WHERE p.productID IN ({})
         ", retail, int, profile_id, param.company_id, ids.join(","));
-        let mut db = match DB::new(&init.db_b2b, Arc::clone(&log_clone)) {
-            Some(db) => db,
-            None => return Err(log.client_err(27)),
+        // Жоден товар не пройшов фільтр, запит з порожнім IN не виконується
+        let result = if ids.len() == 0 {
+            Some(Vec::new())
+        } else {
+            let mut db = match DB::new(&init.db_b2b, Arc::clone(&log_clone)) {
+                Some(db) => db,
+                None => return Err(Error::PriceQuery),
+            };
+            db.query(&sql)
         };
-
-        match db.query(&sql) {
+        match result {
             Some(result) => {
                 let row: Vec<(u32, f32, u32, f32, f32, f32)> = result;
                 for (product_id, price_usd, price_ind,recommended_price, retail_price, internet_price) in row {
@@ -766,34 +1223,63 @@ impl Price {
                     }
                 }
             },
-            None => return Err(log.client_err(28)),
+            None => return Err(Error::PriceRead),
         }
-
-        if cfg!(debug_assertions) {
-            println!("{} Start format price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string());
+        if param.filter.price_min.is_some() || param.filter.price_max.is_some() {
+            let uah = param.volume == PriceVolume::FullUAH;
+            self.items.retain(|_, p| param.filter.price(if uah { p.price_uah } else { p.price_usd }));
         }
-        let res = match param.format {
-            Format::XLSX => match FormatXLSX::make(&self.items, file, &param.volume, rozn, r3, param.ean, Arc::clone(&init_clone)) {
-                Some(res) => Ok(res),
-                None => Err(log.client_err(29)),
-            },
-            Format::PHP => match FormatPHP::make(&self.items, file, &param.volume, rozn, r3, param.ean, Arc::clone(&init_clone)) {
-                Some(res) => Ok(res),
-                None => Err(log.client_err(30)),
+
+        // Для вибраних товарів дерево категорій не додається
+        let categories = if file.is_some() {
+            let c = Mutex::lock(&category).unwrap();
+            c.list.clone()
+        } else {
+            Vec::new()
+        };
+        let items = self.sorted(param, category);
+        let show = Show::new(&init.columns);
+        let columns = show.get(columns);
+
+        let log_read = RwLock::read(&log_clone).unwrap();
+        let span = log_read.span("format price", Some(&self.ctx));
+        let mut formatter = param.format.formatter(param);
+        let res = match file {
+            Some((file, meta)) => {
+                meta.rows = Some(items.len());
+                formatter.make(file, meta, &columns, &items, &categories, Arc::clone(&init_clone))
             },
-            Format::XML => match FormatXml::make(&self.items, file, &param.volume, rozn, r3, param.ean, &param.lang, Arc::clone(&init_clone), Arc::clone(&log_clone)) {
-                Some(res) => Ok(res),
-                None => Err(log.client_err(31)),
+            None => formatter.render(&columns, &items, &categories),
+        };
+        let res = match res {
+            Some(res) => Ok(res),
+            None => Err(param.format.error()),
+        };
+        drop(span);
+        res
+    }
+
+    // Рядки прайса в стабільному порядку, однаковому для всіх форматів
+    fn sorted(&self, param: &Param, category: Arc<Mutex<Categories>>) -> Vec<&PriceItem> {
+        let mut list: Vec<&PriceItem> = self.items.values().collect();
+        match param.sort {
+            Sort::Category => {
+                let c = Mutex::lock(&category).unwrap();
+                list.sort_by(|a, b| c.order(a.category_id).cmp(&c.order(b.category_id))
+                    .then_with(|| a.vendor.cmp(&b.vendor))
+                    .then_with(|| a.name.cmp(&b.name))
+                    .then(a.id.cmp(&b.id)));
             },
-            Format::JSON => match FormatJSON::make(&self.items, file, &param.volume, rozn, r3, param.ean, Arc::clone(&init_clone)) {
-                Some(res) => Ok(res),
-                None => Err(log.client_err(32)),
+            Sort::Code => list.sort_by(|a, b| a.code.cmp(&b.code).then(a.id.cmp(&b.id))),
+            Sort::Price => {
+                let uah = param.volume == PriceVolume::FullUAH;
+                list.sort_by(|a, b| {
+                    let (pa, pb) = if uah { (a.price_uah, b.price_uah) } else { (a.price_usd, b.price_usd) };
+                    pa.total_cmp(&pb).then_with(|| a.code.cmp(&b.code)).then(a.id.cmp(&b.id))
+                });
             },
-        };
-        if cfg!(debug_assertions) {
-            println!("{} Finish format price", chrono::Local::now().format("%Y.%m.%d %H:%M:%S%.9f").to_string());
         }
-        res
+        list
     }
 
     fn round6(price: f32) -> f32 {
@@ -802,5 +1288,41 @@ impl Price {
         price = price.floor() * 6.0;
         price / 100.0
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NDJSON: &[u8] = b"{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n{\"id\":4}\n{\"id\":5}\n";
+
+    fn page(total: Option<usize>, offset: usize, limit: usize) -> String {
+        let answer = Price::page(NDJSON, total, Page { offset, limit, generation: None }, 9).unwrap();
+        String::from_utf8(answer.body).unwrap()
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn page_slice() {
+        assert_eq!(page(None, 0, 2), "{\"generation\":9,\"total\":5,\"offset\":0,\"limit\":2,\"next\":\"9.2\",\"items\":[{\"id\":1},{\"id\":2}]}");
+        assert_eq!(page(Some(5), 2, 2), "{\"generation\":9,\"total\":5,\"offset\":2,\"limit\":2,\"next\":\"9.4\",\"items\":[{\"id\":3},{\"id\":4}]}");
+        assert_eq!(page(Some(5), 4, 2), "{\"generation\":9,\"total\":5,\"offset\":4,\"limit\":2,\"next\":null,\"items\":[{\"id\":5}]}");
+        assert_eq!(page(None, 10, 2), "{\"generation\":9,\"total\":5,\"offset\":5,\"limit\":2,\"next\":null,\"items\":[]}");
+    }
+
+    #[test]
+    fn page_stale_cursor() {
+        let before = crate::cache::Cache::next_generation(0);
+        let answer = Price::page(NDJSON, Some(5), Page { offset: 0, limit: 2, generation: None }, before).unwrap();
+        let next = format!("\"next\":\"{}\"", Page::cursor(before, 2));
+        assert!(String::from_utf8(answer.body).unwrap().contains(&next));
+        let cursor = Page { offset: 2, limit: 2, generation: Some(before) };
+        assert!(!cursor.stale(before));
+        // Після перезапуску сервера перше завантаження має інше покоління
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let after = crate::cache::Cache::next_generation(0);
+        assert!(cursor.stale(after));
+        assert!(cursor.stale(crate::cache::Cache::next_generation(before)));
+        // Сторінка за offset не прив'язана до покоління
+        assert!(!Page { offset: 2, limit: 2, generation: None }.stale(after));
+    }
+}
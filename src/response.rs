@@ -0,0 +1,85 @@
+// Відповідь WEB серверу у форматі CGI (RFC 3875, розділ 6)
+//
+// Status: 200 OK
+// Content-Type: application/json
+// Content-Length: 123
+//
+// <тіло відповіді>
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    stderr: Vec<String>,                // Діагностика для журналу WEB сервера
+}
+
+impl Response {
+    pub fn new(status: u16) -> Response {
+        Response {
+            status,
+            headers: Vec::with_capacity(8),
+            body: Vec::new(),
+            stderr: Vec::new(),
+        }
+    }
+
+    // Додати заголовок, повторний заголовок з тією ж назвою замінює попередній
+    pub fn header(&mut self, name: &str, value: &str) {
+        match self.headers.iter_mut().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            Some(item) => item.1 = value.to_owned(),
+            None => self.headers.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    pub fn body(&mut self, body: Vec<u8>) {
+        self.body = body;
+    }
+
+    pub fn stderr(&mut self, text: &str) {
+        self.stderr.push(text.to_owned());
+    }
+
+    // Потік Stdout: заголовки та тіло
+    pub fn stdout(&self) -> Vec<u8> {
+        let mut head = String::with_capacity(256);
+        head.push_str(&format!("Status: {} {}\r\n", self.status, Response::status_text(self.status)));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if self.status != 304 {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("\r\n");
+        let mut data = Vec::with_capacity(head.len() + self.body.len());
+        data.extend_from_slice(head.as_bytes());
+        data.extend_from_slice(&self.body[..]);
+        data
+    }
+
+    // Потік Stderr, порожній якщо діагностики немає
+    pub fn stderr_data(&self) -> Vec<u8> {
+        let mut data = String::new();
+        for text in &self.stderr {
+            data.push_str("brain_b2b: ");
+            data.push_str(text);
+            data.push('\n');
+        }
+        data.into_bytes()
+    }
+
+    pub fn status_text(status: u16) -> &'static str {
+        match status {
+            200 => "OK",
+            304 => "Not Modified",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            410 => "Gone",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "Unknown",
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::{sync::{Arc, Mutex, RwLock}, thread::{self, JoinHandle}, time::Duration, collections::HashMap};
+
+use chrono::Local;
+
+use crate::{go::Go, init::Init, log::Log, worker::Worker, price::Price, cache::Cache};
+
+pub const MS1000: std::time::Duration = Duration::from_millis(1000);
+pub const HISTORY_MAX: usize = 10000;               // Максимальна кількість різних запитів в історії
+pub const HISTORY_AGE: i64 = 24 * 60 * 60;          // Запити старші за добу не враховуються (с)
+
+// Запит прайса в історії
+#[derive(Debug)]
+struct HistoryItem {
+    count: u32,
+    last: i64,                          // Час останнього запиту (unix)
+}
+
+// Історія успішних запитів прайсів для визначення популярних комбінацій параметрів
+#[derive(Debug)]
+pub struct History {
+    list: HashMap<String, HistoryItem>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            list: HashMap::with_capacity(1024),
+        }
+    }
+
+    // query - канонічний рядок запиту без time та token
+    pub fn add(&mut self, query: String) {
+        let now = Local::now().timestamp();
+        if self.list.len() >= HISTORY_MAX && !self.list.contains_key(&query) {
+            self.list.retain(|_, item| now - item.last < HISTORY_AGE);
+            if self.list.len() >= HISTORY_MAX {
+                return;
+            }
+        }
+        let item = self.list.entry(query).or_insert(HistoryItem { count: 0, last: now });
+        item.count += 1;
+        item.last = now;
+    }
+
+    // Найпопулярніші запити за останню добу
+    pub fn top(&mut self, max: usize) -> Vec<String> {
+        let now = Local::now().timestamp();
+        self.list.retain(|_, item| now - item.last < HISTORY_AGE);
+        let mut list: Vec<(&String, &HistoryItem)> = self.list.iter().collect();
+        list.sort_by(|a, b| b.1.count.cmp(&a.1.count));
+        list.into_iter().take(max).map(|(query, _)| query.clone()).collect()
+    }
+}
+
+// Попередня генерація популярних прайсів після кожного завантаження Cache
+#[derive(Debug)]
+pub struct Warmup {
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Warmup {
+    pub fn new(go: Arc<Mutex<Go>>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Arc<Mutex<Warmup>> {
+        let warmup = Arc::new(Mutex::new(Warmup {
+            thread: None,
+        }));
+        let thread = thread::spawn(move || {
+            let worker = Worker::detached(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
+            let cache;
+            {
+                let g = Mutex::lock(&go).unwrap();
+                cache = Arc::clone(g.cache.as_ref().unwrap());
+            }
+            let mut done = 0;
+            loop {
+                if Warmup::stop(Arc::clone(&go)) {
+                    break;
+                }
+                let generation = Warmup::generation(Arc::clone(&cache));
+                if generation != done {
+                    done = generation;
+                    Warmup::run(Arc::clone(&go), Arc::clone(&worker), generation, Arc::clone(&init), Arc::clone(&log));
+                }
+                thread::sleep(MS1000);
+            }
+        });
+        {
+            let mut w = Mutex::lock(&warmup).unwrap();
+            w.thread.replace(thread);
+        }
+        warmup
+    }
+
+    pub fn join(warmup: Arc<Mutex<Warmup>>) {
+        let thread;
+        {
+            let mut w = Mutex::lock(&warmup).unwrap();
+            thread = w.thread.take();
+        }
+        if let Some(thread) = thread {
+            thread.join().unwrap();
+        }
+    }
+
+    fn stop(go: Arc<Mutex<Go>>) -> bool {
+        let g = Mutex::lock(&go).unwrap();
+        g.stop
+    }
+
+    fn generation(cache: Arc<Mutex<Cache>>) -> u64 {
+        let c = Mutex::lock(&cache).unwrap();
+        c.generation
+    }
+
+    // Згенерувати прайси з конфігурації та найпопулярніші з історії.
+    // Прайси генеруються по одному, лише коли зайнято менше половини робочих потоків
+    fn run(go: Arc<Mutex<Go>>, worker: Arc<Mutex<Worker>>, generation: u64, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) {
+        let mut list;
+        let top;
+        let pause;
+        let max;
+        let history;
+        let cache;
+        {
+            let g = Mutex::lock(&go).unwrap();
+            history = Arc::clone(&g.history);
+            cache = Arc::clone(g.cache.as_ref().unwrap());
+        }
+        {
+            let i = RwLock::read(&init).unwrap();
+            list = i.warmup.clone();
+            top = i.warmup_top as usize;
+            pause = Duration::from_millis(u64::from(i.warmup_pause));
+            max = i.max;
+        }
+        if top > 0 {
+            let mut h = Mutex::lock(&history).unwrap();
+            for query in h.top(top) {
+                if !list.contains(&query) {
+                    list.push(query);
+                }
+            }
+        }
+        if list.len() == 0 {
+            return;
+        }
+
+        let l = RwLock::read(&log).unwrap();
+        let _span = l.span("warmup", None);
+        let mut made = 0;
+        for query in list {
+            loop {
+                if Warmup::stop(Arc::clone(&go)) {
+                    return;
+                }
+                {
+                    let g = Mutex::lock(&go).unwrap();
+                    if g.use_connection * 2 < max {
+                        break;
+                    }
+                }
+                thread::sleep(MS1000);
+            }
+            let mut price = Price::new(Arc::clone(&worker));
+            match price.warm(&query) {
+                Ok(true) => {
+                    made += 1;
+                    thread::sleep(pause);
+                },
+                Ok(false) => {},
+                Err(err) => l.debug(&format!("warmup {}: {}", query, err.message()), None),
+            }
+            // Дані оновились, прогрів почнеться заново для нового покоління
+            if Warmup::generation(Arc::clone(&cache)) != generation {
+                break;
+            }
+        }
+        l.info(&format!("warmup generation {}: {} prices", generation, made), None);
+    }
+}
@@ -1,6 +1,6 @@
-use std::{net::TcpStream, sync::{Mutex, Arc, mpsc, RwLock}, thread::{JoinHandle, self}, collections::HashMap};
+use std::{net::TcpStream, sync::{Mutex, Arc, mpsc::{self, Sender}, RwLock}, thread::{JoinHandle, self}, collections::HashMap};
 
-use crate::{go::Go, init::Init, log::Log, fastcgi::{Record, FASTCGI_MAX_REQUEST_LEN, FastCGI, RecordType, HeaderType, ContentData}, price::Price, cache::Cache};
+use crate::{go::Go, init::Init, log::Log, fastcgi::{Record, FASTCGI_MAX_REQUEST_LEN, FastCGI, RecordType, HeaderType, ContentData}, price::Price, cache::Cache, limit::Limit, audit::AuditRecord, error::Error, flight::Flight, warmup::History};
 
 #[derive(Debug)]
 pub enum Message {
@@ -28,26 +28,15 @@ pub struct Worker {
     pub init: Arc<RwLock<Init>>,
     pub log: Arc<RwLock<Log>>,
     pub cache: Arc<Mutex<Cache>>,
+    pub limit: Arc<Mutex<Limit>>,
+    pub flight: Arc<Flight>,
+    pub audit: Sender<AuditRecord>,
+    pub history: Arc<Mutex<History>>,
 }
 
 impl Worker {
     pub fn new(go: Arc<Mutex<Go>>, receiver: Arc<Mutex<mpsc::Receiver<Message>>>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Arc<Mutex<Worker>> {
-        let cache;
-        {
-            let g = Mutex::lock(&go).unwrap();
-            cache = Arc::clone(&g.cache.as_ref().unwrap());
-        }
-        let worker = Worker {
-            start: false,
-            stop: false,
-            thread: None,
-            status: Status::None,
-            init: Arc::clone(&init),
-            log: Arc::clone(&log),
-            cache,
-        };
-
-        let worker = Arc::new(Mutex::new(worker));
+        let worker = Worker::detached(Arc::clone(&go), Arc::clone(&init), Arc::clone(&log));
         let worker_thread = Arc::clone(&worker);
 
         let thread = thread::spawn(move || {
@@ -73,7 +62,7 @@ impl Worker {
                         },
                         Message::Terminate => break,
                     },
-                    Err(err) => log.exit(500, &err.to_string()),
+                    Err(err) => log.exit(Error::WorkerChannel, &err.to_string()),
                 }
             }
         });
@@ -84,6 +73,38 @@ impl Worker {
         worker
     }
     
+    // Worker без власного потоку та з'єднання, для фонової генерації прайсів
+    pub fn detached(go: Arc<Mutex<Go>>, init: Arc<RwLock<Init>>, log: Arc<RwLock<Log>>) -> Arc<Mutex<Worker>> {
+        let cache;
+        let limit;
+        let flight;
+        let audit;
+        let history;
+        {
+            let g = Mutex::lock(&go).unwrap();
+            cache = Arc::clone(&g.cache.as_ref().unwrap());
+            limit = Arc::clone(&g.limit);
+            flight = Arc::clone(&g.flight);
+            history = Arc::clone(&g.history);
+            let a = Mutex::lock(g.audit.as_ref().unwrap()).unwrap();
+            audit = a.sender.clone().unwrap();
+        }
+        let worker = Worker {
+            start: false,
+            stop: false,
+            thread: None,
+            status: Status::None,
+            init,
+            log,
+            cache,
+            limit,
+            flight,
+            audit,
+            history,
+        };
+        Arc::new(Mutex::new(worker))
+    }
+
     pub fn join(worker: Arc<Mutex<Worker>>) {
         let thread;
         {